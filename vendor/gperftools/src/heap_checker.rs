@@ -0,0 +1,144 @@
+//! Heap Leak Checker
+//!
+//! Bindings to gperftools' `HeapLeakChecker`, which snapshots live
+//! allocations at two points in time and reports what was not freed in
+//! between. This is a different capability from the statistical
+//! `HeapProfiler`: where the profiler samples allocations over the
+//! lifetime of the process, the leak checker proves a code region is (or
+//! is not) leak-free.
+//!
+//! Like the heap profiler, gperftools only supports one leak checker
+//! construction at a time per name; `LeakChecker` does not attempt to
+//! multiplex several concurrent checks.
+//!
+//! # Usage
+//!
+//! ```
+//! use gperftools::heap_checker::LeakChecker;
+//!
+//! let checker = LeakChecker::new("my-region").unwrap();
+//!
+//! // do some work that should not leak
+//! let v = vec![1; 1000];
+//! println!("{:?}", v);
+//! drop(v);
+//!
+//! checker.check().unwrap();
+//! ```
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_void};
+
+use error::{Error, ErrorKind};
+
+#[allow(non_snake_case)]
+extern "C" {
+    fn HeapLeakChecker_New(name: *const c_char) -> *mut c_void;
+
+    fn HeapLeakChecker_Delete(checker: *mut c_void) -> c_int;
+
+    fn HeapLeakChecker_NoGlobalLeaks() -> c_int;
+
+    fn HeapLeakChecker_SameHeap(checker: *mut c_void) -> c_int;
+
+    fn HeapLeakChecker_BytesLeaked(checker: *mut c_void) -> i64;
+
+    fn HeapLeakChecker_ObjectsLeaked(checker: *mut c_void) -> i64;
+}
+
+/// The result of comparing live allocations against a `LeakChecker`'s
+/// starting snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeakReport {
+    /// Bytes allocated since the checker was created and not yet freed.
+    pub bytes: i64,
+    /// Number of distinct allocations since the checker was created and
+    /// not yet freed.
+    pub objects: i64,
+}
+
+impl LeakReport {
+    /// True if the checker observed no leaked bytes or objects.
+    pub fn is_clean(&self) -> bool {
+        self.bytes == 0 && self.objects == 0
+    }
+}
+
+/// A named, scoped leak checker.
+///
+/// Dropping a `LeakChecker` does not check for leaks by itself; call
+/// [`check`](LeakChecker::check) explicitly at the point where the
+/// region under test should be leak-free.
+#[derive(Debug)]
+pub struct LeakChecker {
+    handle: *mut c_void,
+}
+
+// The underlying gperftools checker is safe to hand across threads; it is
+// only ever touched through the `HeapLeakChecker_*` C functions taking the
+// handle by value.
+unsafe impl Send for LeakChecker {}
+
+impl LeakChecker {
+    /// Create a new named leak checker, snapshotting currently live
+    /// allocations.
+    ///
+    /// # Failures
+    ///
+    /// - `name` is not a valid `CString`.
+    /// - gperftools failed to construct the checker (e.g. heap checking
+    ///   is disabled for this build).
+    pub fn new<T: Into<Vec<u8>>>(name: T) -> Result<LeakChecker, Error> {
+        let c_name = try!(CString::new(name));
+        let handle = unsafe { HeapLeakChecker_New(c_name.as_ptr()) };
+
+        if handle.is_null() {
+            Err(ErrorKind::InternalError.into())
+        } else {
+            Ok(LeakChecker { handle })
+        }
+    }
+
+    /// Compare current live allocations against the snapshot taken at
+    /// construction and report what leaked.
+    ///
+    /// # Failures
+    ///
+    /// Returns `Err` with the leaked bytes/objects if anything leaked.
+    pub fn check(&self) -> Result<(), LeakReport> {
+        let report = self.report();
+        if report.is_clean() {
+            Ok(())
+        } else {
+            Err(report)
+        }
+    }
+
+    /// Report bytes/objects leaked since construction, without treating a
+    /// leak as an error.
+    pub fn report(&self) -> LeakReport {
+        unsafe {
+            LeakReport { bytes: HeapLeakChecker_BytesLeaked(self.handle),
+                         objects: HeapLeakChecker_ObjectsLeaked(self.handle) }
+        }
+    }
+
+    /// True if the live heap is identical (same allocations) to the one
+    /// at construction.
+    pub fn same_heap(&self) -> bool {
+        unsafe { HeapLeakChecker_SameHeap(self.handle) == 1 }
+    }
+}
+
+impl Drop for LeakChecker {
+    fn drop(&mut self) {
+        unsafe {
+            HeapLeakChecker_Delete(self.handle);
+        }
+    }
+}
+
+/// Whole-program check: true if no global leaks have been detected so far.
+pub fn no_global_leaks() -> bool {
+    unsafe { HeapLeakChecker_NoGlobalLeaks() == 1 }
+}
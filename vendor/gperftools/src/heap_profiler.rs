@@ -35,14 +35,18 @@
 //! We limit access this way to ensure that only one profiler is running at a time -
 //! this is a limitation of the heap-profiler library.
 
-use std::ffi::CString;
-use std::os::raw::{c_char, c_int};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
 use std::sync::Mutex;
 
 use error::{Error, ErrorKind};
 use state::ProfilerState;
 use util::check_file_path;
 
+extern "C" {
+    fn free(ptr: *mut c_void);
+}
+
 lazy_static! {
     /// Static reference to the HEAP_PROFILER
     ///
@@ -51,6 +55,7 @@ lazy_static! {
     #[derive(Debug)]
     pub static ref HEAP_PROFILER: Mutex<HeapProfiler> = Mutex::new(HeapProfiler {
         state: ProfilerState::NotActive,
+        saved_env: Vec::new(),
     });
 }
 
@@ -63,6 +68,62 @@ extern "C" {
     fn HeapProfilerDump(resaon: *const c_char);
 
     fn IsHeapProfilerRunning() -> c_int;
+
+    fn GetHeapProfile() -> *mut c_char;
+}
+
+/// Programmatic equivalent of the `HEAP_PROFILE_*` environment variables
+/// read once by `HeapProfilerStart`.
+///
+/// Any field left as `None` leaves the corresponding environment variable
+/// (and therefore gperftools' built-in default) untouched.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HeapProfilerOptions {
+    /// `HEAP_PROFILE_ALLOCATION_INTERVAL`
+    pub allocation_interval: Option<u64>,
+    /// `HEAP_PROFILE_DEALLOCATION_INTERVAL`
+    pub deallocation_interval: Option<u64>,
+    /// `HEAP_PROFILE_INUSE_INTERVAL`
+    pub inuse_interval: Option<u64>,
+    /// `HEAP_PROFILE_TIME_INTERVAL`
+    pub time_interval: Option<u64>,
+    /// `HEAP_PROFILE_MMAP`
+    pub profile_mmap: Option<bool>,
+    /// `HEAP_PROFILE_ONLY_MMAP`
+    pub only_mmap: Option<bool>,
+    /// `HEAP_PROFILE_MMAP_LOG`
+    pub mmap_log: Option<bool>,
+}
+
+impl HeapProfilerOptions {
+    // Pairs of (env var name, current value to set), skipping unset fields.
+    fn env_vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = Vec::new();
+
+        if let Some(v) = self.allocation_interval {
+            vars.push(("HEAP_PROFILE_ALLOCATION_INTERVAL", v.to_string()));
+        }
+        if let Some(v) = self.deallocation_interval {
+            vars.push(("HEAP_PROFILE_DEALLOCATION_INTERVAL", v.to_string()));
+        }
+        if let Some(v) = self.inuse_interval {
+            vars.push(("HEAP_PROFILE_INUSE_INTERVAL", v.to_string()));
+        }
+        if let Some(v) = self.time_interval {
+            vars.push(("HEAP_PROFILE_TIME_INTERVAL", v.to_string()));
+        }
+        if let Some(v) = self.profile_mmap {
+            vars.push(("HEAP_PROFILE_MMAP", (v as u8).to_string()));
+        }
+        if let Some(v) = self.only_mmap {
+            vars.push(("HEAP_PROFILE_ONLY_MMAP", (v as u8).to_string()));
+        }
+        if let Some(v) = self.mmap_log {
+            vars.push(("HEAP_PROFILE_MMAP_LOG", (v as u8).to_string()));
+        }
+
+        vars
+    }
 }
 
 /// The `HeapProfiler`
@@ -73,6 +134,9 @@ extern "C" {
 #[derive(Debug)]
 pub struct HeapProfiler {
     state: ProfilerState,
+    // Environment variables overridden by `start_with_options`, together
+    // with their prior value (if any), so `stop` can restore them.
+    saved_env: Vec<(&'static str, Option<String>)>,
 }
 
 impl HeapProfiler {
@@ -134,6 +198,42 @@ impl HeapProfiler {
         }
     }
 
+    /// Start the heap profiler with explicit `HeapProfilerOptions`.
+    ///
+    /// This is equivalent to setting the corresponding `HEAP_PROFILE_*`
+    /// environment variables immediately before calling `start`, except
+    /// that the prior value of any variable touched here (if any) is
+    /// restored once `stop` is called, instead of leaking into the rest
+    /// of the process' lifetime.
+    ///
+    /// # Failures
+    ///
+    /// Same as [`start`](HeapProfiler::start).
+    pub fn start_with_options<T: Into<Vec<u8>>>(&mut self,
+                                                 fname: T,
+                                                 opts: HeapProfilerOptions)
+                                                 -> Result<(), Error> {
+        if self.state != ProfilerState::NotActive {
+            return Err(ErrorKind::InvalidState(self.state).into());
+        }
+
+        let c_fname = try!(CString::new(fname));
+        check_file_path(c_fname.clone().into_string().unwrap())?;
+
+        let mut saved_env = Vec::new();
+        for (name, value) in opts.env_vars() {
+            saved_env.push((name, std::env::var(name).ok()));
+            std::env::set_var(name, value);
+        }
+
+        unsafe {
+            HeapProfilerStart(c_fname.as_ptr());
+        }
+        self.state = ProfilerState::Active;
+        self.saved_env = saved_env;
+        Ok(())
+    }
+
     /// Stop the heap profiler.
     ///
     /// This will stop the profiler if it `Active` and return
@@ -148,6 +248,14 @@ impl HeapProfiler {
                 HeapProfilerStop();
             }
             self.state = ProfilerState::NotActive;
+
+            for (name, value) in self.saved_env.drain(..) {
+                match value {
+                    Some(v) => std::env::set_var(name, v),
+                    None => std::env::remove_var(name),
+                }
+            }
+
             Ok(())
         } else {
             Err(ErrorKind::InvalidState(self.state).into())
@@ -163,4 +271,30 @@ impl HeapProfiler {
         }
         Ok(())
     }
+
+    /// Retrieve the current heap profile without writing it to disk.
+    ///
+    /// This calls into gperftools' `GetHeapProfile`, which serializes the
+    /// profile into a heap-allocated, `NUL`-terminated C string. The
+    /// returned bytes are copied into an owned `String` and the original
+    /// pointer is freed before returning, so callers never have to manage
+    /// the underlying allocation themselves.
+    ///
+    /// # Failures
+    ///
+    /// - `GetHeapProfile` returned a null pointer.
+    /// - The profile bytes are not valid UTF-8.
+    pub fn get_profile(&self) -> Result<String, Error> {
+        unsafe {
+            let ptr = GetHeapProfile();
+            if ptr.is_null() {
+                return Err(ErrorKind::InternalError.into());
+            }
+
+            let profile = CStr::from_ptr(ptr).to_str().map(|s| s.to_owned());
+            free(ptr as *mut c_void);
+
+            Ok(try!(profile))
+        }
+    }
 }
@@ -21,3 +21,7 @@ pub use heap_profiler::*;
 mod tcmalloc;
 #[cfg(feature = "heap")]
 static GLOBAL: tcmalloc::TCMalloc = tcmalloc::TCMalloc;
+#[cfg(feature = "heap")]
+pub mod malloc_extension;
+#[cfg(feature = "heap")]
+pub mod heap_checker;
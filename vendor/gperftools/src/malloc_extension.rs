@@ -0,0 +1,76 @@
+//! Bindings to gperftools' `MallocExtension` interface.
+//!
+//! This exposes lightweight introspection and control over the tcmalloc
+//! allocator backing [`TCMalloc`](crate::tcmalloc::TCMalloc), without the
+//! overhead of a full heap profiling session.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+
+use error::{Error, ErrorKind};
+
+#[allow(non_snake_case)]
+extern "C" {
+    fn MallocExtension_GetNumericProperty(property: *const c_char, value: *mut usize) -> c_int;
+
+    fn MallocExtension_SetNumericProperty(property: *const c_char, value: usize) -> c_int;
+
+    fn MallocExtension_ReleaseFreeMemory();
+}
+
+/// Read a tcmalloc numeric property by its documented name, e.g.
+/// `"generic.current_allocated_bytes"`.
+///
+/// Returns `Err(ErrorKind::InternalError)` if the property is unknown to
+/// this build of tcmalloc.
+pub fn get_numeric_property(property: &str) -> Result<usize, Error> {
+    let c_property = try!(CString::new(property));
+    let mut value: usize = 0;
+
+    let ok = unsafe { MallocExtension_GetNumericProperty(c_property.as_ptr(), &mut value) };
+
+    if ok == 0 {
+        Err(ErrorKind::InternalError.into())
+    } else {
+        Ok(value)
+    }
+}
+
+/// Set a tcmalloc numeric property by its documented name.
+///
+/// Returns `Err(ErrorKind::InternalError)` if the property is unknown or
+/// not writable.
+pub fn set_numeric_property(property: &str, value: usize) -> Result<(), Error> {
+    let c_property = try!(CString::new(property));
+
+    let ok = unsafe { MallocExtension_SetNumericProperty(c_property.as_ptr(), value) };
+
+    if ok == 0 {
+        Err(ErrorKind::InternalError.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Bytes of memory allocated by the application and not yet freed.
+pub fn current_allocated_bytes() -> Result<usize, Error> {
+    get_numeric_property("generic.current_allocated_bytes")
+}
+
+/// Bytes of memory reserved by tcmalloc, including that not currently in use.
+pub fn heap_size() -> Result<usize, Error> {
+    get_numeric_property("generic.heap_size")
+}
+
+/// Bytes tcmalloc has retrieved from the OS but is not using for the
+/// application heap, and could release back to the OS.
+pub fn pageheap_free_bytes() -> Result<usize, Error> {
+    get_numeric_property("tcmalloc.pageheap_free_bytes")
+}
+
+/// Ask tcmalloc to return any cached, unused pages back to the OS.
+pub fn release_free_memory() {
+    unsafe {
+        MallocExtension_ReleaseFreeMemory();
+    }
+}
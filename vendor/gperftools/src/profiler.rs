@@ -0,0 +1,215 @@
+//! CPU Profiler
+//!
+//!
+//! # Usage
+//!
+//! ```
+//! use gperftools::PROFILER;
+//!
+//! // Start profiling
+//! PROFILER.lock().unwrap().start("./my-prof.profile").unwrap();
+//!
+//! // do some work
+//!
+//! // stop profiling
+//! PROFILER.lock().unwrap().stop().unwrap();
+//! ```
+//!
+//! The profiler is accessed via the static `PROFILER: Mutex<Profiler>`.
+//! We limit access this way to ensure that only one profiler is running at
+//! a time - this is a limitation of the cpu-profiler library.
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+use std::sync::Mutex;
+
+use error::{Error, ErrorKind};
+use state::ProfilerState;
+use util::check_file_path;
+
+lazy_static! {
+    /// Static reference to the `PROFILER`
+    ///
+    /// The cpu-profiler library only supports one active profiler.
+    /// Because of this we must use static access and wrap in a `Mutex`.
+    #[derive(Debug)]
+    pub static ref PROFILER: Mutex<Profiler> = Mutex::new(Profiler {
+        state: ProfilerState::NotActive,
+        saved_frequency_env: None,
+    });
+}
+
+#[allow(non_snake_case)]
+extern "C" {
+    fn ProfilerStart(fname: *const c_char) -> c_int;
+
+    fn ProfilerStartWithOptions(fname: *const c_char, options: *const CProfilerOptions) -> c_int;
+
+    fn ProfilerStop();
+}
+
+/// A per-thread filter used by [`ProfilerOptions`] to decide whether the
+/// calling thread should be sampled. Mirrors gperftools'
+/// `ProfilerOptions::filter_in_thread`.
+pub type ThreadFilter = extern "C" fn(arg: *mut c_void) -> c_int;
+
+#[allow(non_snake_case)]
+#[repr(C)]
+struct CProfilerOptions {
+    filter_in_thread: Option<ThreadFilter>,
+    filter_in_thread_arg: *mut c_void,
+}
+
+/// Options for [`Profiler::start_with_options`].
+///
+/// `frequency_hz` controls how many samples per second the profiler takes;
+/// gperftools' own default (100 Hz) is used when left unset. `filter`, when
+/// set, lets the caller restrict sampling to specific threads.
+pub struct ProfilerOptions {
+    /// Sampling frequency in Hz. `None` keeps the library default (100 Hz).
+    pub frequency_hz: Option<u32>,
+    /// Optional per-thread sampling filter, paired with an opaque argument
+    /// passed back to it on every invocation.
+    pub filter: Option<(ThreadFilter, *mut c_void)>,
+}
+
+impl Default for ProfilerOptions {
+    fn default() -> ProfilerOptions {
+        ProfilerOptions { frequency_hz: None,
+                          filter: None }
+    }
+}
+
+/// The `Profiler`
+///
+/// The `Profiler` gives access to the _cpu-profiler_ library.
+/// By storing the state of the profiler and limiting access
+/// we make the FFI safer.
+#[derive(Debug)]
+pub struct Profiler {
+    state: ProfilerState,
+    // `CPUPROFILE_FREQUENCY`'s prior value (if any), overridden by
+    // `start_with_options`, so `stop` can restore it.
+    saved_frequency_env: Option<Option<String>>,
+}
+
+impl Profiler {
+    /// Returns the profiler state.
+    pub fn state(&self) -> ProfilerState {
+        self.state
+    }
+
+    /// Start the cpu profiler.
+    ///
+    /// Will begin sampling once this function has been called
+    /// and will not stop until the `stop` function has been called.
+    ///
+    /// This function takes as an argument a filename. The filename must be
+    /// both valid Utf8 and a valid `CString`.
+    ///
+    /// # Failures
+    ///
+    /// - The profiler is currently `Active`.
+    /// - `fname` is not a valid `CString`.
+    /// - `fname` is not valid Utf8.
+    /// - `fname` is not a file.
+    /// - The user does not have write access to the file.
+    /// - An internal failure from the gperftools library.
+    pub fn start<T: Into<Vec<u8>>>(&mut self, fname: T) -> Result<(), Error> {
+        if self.state == ProfilerState::NotActive {
+            let c_fname = try!(CString::new(fname));
+            check_file_path(c_fname.clone().into_string().unwrap())?;
+
+            let success = unsafe { ProfilerStart(c_fname.as_ptr()) };
+            if success == 0 {
+                return Err(ErrorKind::InternalError.into());
+            }
+
+            self.state = ProfilerState::Active;
+            Ok(())
+        } else {
+            Err(ErrorKind::InvalidState(self.state).into())
+        }
+    }
+
+    /// Start the cpu profiler with an explicit sampling frequency and/or
+    /// thread filter, via `ProfilerStartWithOptions`.
+    ///
+    /// # Failures
+    ///
+    /// Same as [`start`](Profiler::start).
+    pub fn start_with_options<T: Into<Vec<u8>>>(&mut self,
+                                                 fname: T,
+                                                 opts: ProfilerOptions)
+                                                 -> Result<(), Error> {
+        if self.state != ProfilerState::NotActive {
+            return Err(ErrorKind::InvalidState(self.state).into());
+        }
+
+        let c_fname = try!(CString::new(fname));
+        check_file_path(c_fname.clone().into_string().unwrap())?;
+
+        // gperftools reads the sampling frequency from CPUPROFILE_FREQUENCY
+        // at `ProfilerStart{,WithOptions}` time; there is no field for it
+        // on `ProfilerOptions` itself. Save its prior value so `stop` can
+        // restore it instead of leaking the override for the rest of the
+        // process' lifetime.
+        let saved_frequency_env = if let Some(hz) = opts.frequency_hz {
+            let prior = std::env::var("CPUPROFILE_FREQUENCY").ok();
+            std::env::set_var("CPUPROFILE_FREQUENCY", hz.to_string());
+            Some(prior)
+        } else {
+            None
+        };
+
+        let (filter_in_thread, filter_in_thread_arg) = match opts.filter {
+            Some((f, arg)) => (Some(f), arg),
+            None => (None, ptr::null_mut()),
+        };
+        let c_opts = CProfilerOptions { filter_in_thread, filter_in_thread_arg };
+
+        let success = unsafe { ProfilerStartWithOptions(c_fname.as_ptr(), &c_opts) };
+        if success == 0 {
+            if let Some(prior) = saved_frequency_env {
+                match prior {
+                    Some(v) => std::env::set_var("CPUPROFILE_FREQUENCY", v),
+                    None => std::env::remove_var("CPUPROFILE_FREQUENCY"),
+                }
+            }
+            return Err(ErrorKind::InternalError.into());
+        }
+
+        self.state = ProfilerState::Active;
+        self.saved_frequency_env = saved_frequency_env;
+        Ok(())
+    }
+
+    /// Stop the cpu profiler.
+    ///
+    /// This will stop the profiler if it `Active` and return
+    /// an error otherwise.
+    ///
+    /// # Failures
+    ///
+    /// - The profiler is `NotActive`.
+    pub fn stop(&mut self) -> Result<(), Error> {
+        if self.state == ProfilerState::Active {
+            unsafe {
+                ProfilerStop();
+            }
+            self.state = ProfilerState::NotActive;
+
+            if let Some(prior) = self.saved_frequency_env.take() {
+                match prior {
+                    Some(v) => std::env::set_var("CPUPROFILE_FREQUENCY", v),
+                    None => std::env::remove_var("CPUPROFILE_FREQUENCY"),
+                }
+            }
+
+            Ok(())
+        } else {
+            Err(ErrorKind::InvalidState(self.state).into())
+        }
+    }
+}
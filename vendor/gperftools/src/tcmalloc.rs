@@ -1,13 +1,21 @@
 use std::alloc::{GlobalAlloc, Layout};
 use std::os::raw::c_void;
+use std::mem;
 
 #[allow(non_snake_case)]
 extern "C" {
     fn tc_memalign(alignment: usize, size: usize) -> *mut c_void;
     // fn tc_free(ptr: *mut c_void);
     fn tc_free_sized(ptr: *mut c_void, size: usize);
+    fn tc_realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+    fn tc_calloc(n: usize, size: usize) -> *mut c_void;
 }
 
+// tcmalloc's default allocation functions (and therefore `tc_realloc` /
+// `tc_calloc`) only guarantee this much alignment; anything stricter must
+// fall back to the memalign-based path so over-aligned types stay correct.
+const TCMALLOC_DEFAULT_ALIGNMENT: usize = mem::size_of::<*const ()>() * 2;
+
 pub struct TCMalloc;
 
 unsafe impl GlobalAlloc for TCMalloc {
@@ -18,4 +26,35 @@ unsafe impl GlobalAlloc for TCMalloc {
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
         tc_free_sized(ptr as *mut c_void, layout.size());
     }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if layout.align() <= TCMALLOC_DEFAULT_ALIGNMENT {
+            tc_calloc(1, layout.size()) as *mut u8
+        } else {
+            // `tc_calloc` cannot honor over-alignment; fall back to the
+            // default alloc-then-zero path.
+            let ptr = self.alloc(layout);
+            if !ptr.is_null() {
+                core::ptr::write_bytes(ptr, 0, layout.size());
+            }
+            ptr
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if layout.align() <= TCMALLOC_DEFAULT_ALIGNMENT {
+            tc_realloc(ptr as *mut c_void, new_size) as *mut u8
+        } else {
+            // `tc_realloc` does not honor over-alignment either; fall
+            // back to the default alloc-copy-free path.
+            let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+            let new_ptr = self.alloc(new_layout);
+            if !new_ptr.is_null() {
+                let copy_size = core::cmp::min(layout.size(), new_size);
+                core::ptr::copy_nonoverlapping(ptr, new_ptr, copy_size);
+                self.dealloc(ptr, layout);
+            }
+            new_ptr
+        }
+    }
 }
@@ -28,8 +28,8 @@ use std::fmt::{self, Display};
 use std::fs::{File, OpenOptions};
 use std::io::{self, Read, Write};
 use std::ops::{Index, IndexMut};
-use std::path::Path;
-use std::str::Chars;
+use std::path::{Path, PathBuf};
+use std::str::{Chars, FromStr};
 
 #[cfg(feature = "preserve_order")]
 use indexmap::map::{Entry, IndexMap as Map, IntoIter, Iter, IterMut, Keys};
@@ -37,6 +37,7 @@ use indexmap::map::{Entry, IndexMap as Map, IntoIter, Iter, IterMut, Keys};
 use multimap::MultiMap;
 #[cfg(not(feature = "preserve_order"))]
 use std::collections::hash_map::{Entry, HashMap as Map, IntoIter, Iter, IterMut, Keys};
+use std::collections::HashSet;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum EscapePolicy {
@@ -141,7 +142,64 @@ fn escape_str(s: &str, policy: EscapePolicy) -> String {
     escaped
 }
 
+// True if `s` needs surrounding quotes to round-trip under `policy`:
+// leading/trailing whitespace (which the parser otherwise trims), a
+// leading quote character (which the parser would otherwise try to parse
+// as the start of a quoted value), an embedded newline, an embedded `=`
+// (the default key/value delimiter -- quoted regardless of `policy`, since
+// a value that merely looks like another `key=value` pair should not
+// depend on an opt-in escape policy to round-trip), or a symbol the
+// policy would otherwise escape.
+fn needs_quoting(s: &str, policy: EscapePolicy) -> bool {
+    let has_boundary_whitespace = s.starts_with(|c: char| c.is_whitespace())
+                                   || s.ends_with(|c: char| c.is_whitespace());
+
+    has_boundary_whitespace
+    || s.starts_with('"')
+    || s.starts_with('\'')
+    || s.contains('\n')
+    || s.contains('\r')
+    || s.contains('=')
+    || s.chars().any(|c| policy.should_escape(c) && matches!(c, ';' | '#' | '=' | ':'))
+}
+
+// Wrap `s` in double quotes, escaping only what would otherwise break the
+// quoted form (quotes, backslashes, and control characters that can't
+// appear literally in a quoted value).
+fn quote_str(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Render a value for writing, honoring `opt.quote_style`.
+fn write_value_str(s: &str, opt: &WriteOption) -> String {
+    let should_quote = match opt.quote_style {
+        QuoteStyle::Always => true,
+        QuoteStyle::Never => false,
+        QuoteStyle::WhenNeeded => needs_quoting(s, opt.escape_policy),
+    };
+
+    if should_quote {
+        quote_str(s)
+    } else {
+        escape_str(s, opt.escape_policy)
+    }
+}
+
 /// Parsing configuration
+#[derive(Clone)]
 pub struct ParseOption {
     /// Allow quote (" or ') in value
     /// For example
@@ -165,13 +223,164 @@ pub struct ParseOption {
     ///
     /// If `enabled_escape` is true, then the value of `Key` will become `C:Windows` (`\W` equals to `W`).
     pub enabled_escape: bool,
+
+    /// Resolve `[include]` / `[includeIf "<condition>"]` directives.
+    ///
+    /// When enabled, a `path` key found inside an `[include]` section (or
+    /// an `[includeIf "<condition>"]` section whose condition is met) is
+    /// treated as a path to another INI file, resolved relative to
+    /// `include_base_dir`, and merged into the result once the whole file
+    /// has been parsed: keys from the included file override same-named
+    /// keys of the including file, whether those appear before or after
+    /// the directive. See [`IncludeError`] for the ways this can fail.
+    pub enable_includes: bool,
+
+    /// Base directory used to resolve relative `path`s found in `[include]`
+    /// sections. Required when `enable_includes` is set; file-based loads
+    /// (`load_from_file_opt`) default this to the parent directory of the
+    /// file being loaded, but string-based loads (`load_from_str_opt`) have
+    /// no file of their own and must set this explicitly.
+    pub include_base_dir: Option<PathBuf>,
+
+    /// Allow a comment marker to end a value partway through a line, e.g.
+    /// `key = value ; trailing comment`, rather than requiring comments to
+    /// start their own line. Defaults to whatever the `inline_comment`
+    /// feature flag was compiled with, so existing callers see no change.
+    pub inline_comment: bool,
+
+    /// Characters that start a comment when encountered outside a value.
+    /// Defaults to `[';', '#']`.
+    pub comment_markers: Vec<char>,
+
+    /// Characters that separate a key from its value. Defaults to
+    /// `['=', ':']`.
+    pub delimiters: Vec<char>,
+
+    /// Treat a `\` immediately followed by a line break as a line
+    /// continuation: the backslash and the line break are discarded,
+    /// leading whitespace on the next physical line is skipped, and
+    /// parsing continues into the same value. For example
+    /// ```ini
+    /// [Section]
+    /// Key1=part1 \
+    ///     part2
+    /// ```
+    /// parses to `part1 part2` when `enabled_continuation` is `true`.
+    /// This matches the line-continuation semantics of git-config and
+    /// Python's configparser. Defaults to `false`.
+    pub enabled_continuation: bool,
 }
 
 impl Default for ParseOption {
     fn default() -> ParseOption {
         ParseOption { enabled_quote: true,
-                      enabled_escape: true }
+                      enabled_escape: true,
+                      enable_includes: false,
+                      include_base_dir: None,
+                      inline_comment: cfg!(feature = "inline_comment"),
+                      comment_markers: vec![';', '#'],
+                      delimiters: vec!['=', ':'],
+                      enabled_continuation: false }
+    }
+}
+
+/// Maximum include depth before `enable_includes` resolution gives up and
+/// reports a likely cycle (an explicit cycle, via canonicalized path
+/// tracking, is also detected and reported directly).
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// Options for [`Ini::load_from_file_with_includes`].
+///
+/// This is a configurable alternative to [`ParseOption::enable_includes`],
+/// which always resolves includes via a hardcoded `"include"` /
+/// `"includeIf"` section name and [`MAX_INCLUDE_DEPTH`]. Use this instead
+/// when the directive name needs to differ (e.g. to mirror some other
+/// format's own include syntax) or the recursion depth needs tuning; the
+/// underlying cycle-detection and section-merge behavior is the same
+/// either way.
+#[derive(Clone)]
+pub struct IncludeOption {
+    /// Section name that triggers an include, e.g. `"include"` for
+    /// `[include]\npath = ...`. A section named `"<directive>If \"<cond>\""`
+    /// is also honored, mirroring git-config's `includeIf`.
+    pub directive: String,
+
+    /// Base directory used to resolve relative `path`s. `None` defaults to
+    /// the parent directory of the file passed to
+    /// [`Ini::load_from_file_with_includes`].
+    pub base_dir: Option<PathBuf>,
+
+    /// Maximum include recursion depth before giving up with a
+    /// likely-cycle error.
+    pub max_depth: usize,
+}
+
+impl Default for IncludeOption {
+    fn default() -> IncludeOption {
+        IncludeOption { directive: "include".to_owned(),
+                        base_dir: None,
+                        max_depth: MAX_INCLUDE_DEPTH }
+    }
+}
+
+fn include_if_condition<'a>(directive: &str, section_name: &'a str) -> Option<&'a str> {
+    let prefix = format!("{}If", directive);
+    let rest = section_name.strip_prefix(prefix.as_str())?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    rest.strip_suffix('"')
+}
+
+// Very small glob: `*` matches any run of characters, everything else must
+// match literally. This is enough for the common `gitdir:/home/*/work/`
+// style patterns without pulling in a dependency.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*').peekable();
+    let mut text = text;
+
+    if let Some(first) = parts.peek() {
+        if !pattern.starts_with('*') {
+            match text.strip_prefix(first.as_ref() as &str) {
+                Some(rest) => text = rest,
+                None => return false,
+            }
+            parts.next();
+        }
+    }
+
+    let rest: Vec<&str> = parts.collect();
+    for (i, part) in rest.iter().enumerate() {
+        if part.is_empty() {
+            continue;
+        }
+        // The final literal part must sit at the very end of `text`.
+        if i + 1 == rest.len() && !pattern.ends_with('*') {
+            return text.ends_with(part);
+        }
+        match text.find(part) {
+            Some(idx) => text = &text[idx + part.len()..],
+            None => return false,
+        }
+    }
+
+    pattern.ends_with('*') || text.is_empty()
+}
+
+fn include_if_matches(condition: &str) -> bool {
+    if let Some(pattern) = condition.strip_prefix("gitdir:") {
+        return match std::env::current_dir() {
+            Ok(dir) => glob_match(pattern, &dir.to_string_lossy()),
+            Err(_) => false,
+        };
+    }
+
+    if let Some(branch) = condition.strip_prefix("onbranch:") {
+        return match std::fs::read_to_string(".git/HEAD") {
+            Ok(head) => head.trim().ends_with(&format!("refs/heads/{}", branch)),
+            Err(_) => false,
+        };
     }
+
+    false
 }
 
 /// Newline style
@@ -213,6 +422,32 @@ impl LineSeparator {
     }
 }
 
+/// Controls when a written value is wrapped in double quotes rather than
+/// backslash-escaped in place.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum QuoteStyle {
+    /// Never wrap values in quotes; rely solely on `escape_policy`.
+    ///
+    /// Note that with `Never`, a value with leading/trailing whitespace,
+    /// or one starting with `"` or `'`, round-trips incorrectly (the
+    /// whitespace is trimmed back out, or the leading quote character is
+    /// mistaken for the start of a quoted value, on the next load), since
+    /// there is no quote to mark its boundary. Not the default for this
+    /// reason; opt in explicitly if byte-identical output is required.
+    Never,
+    /// Wrap a value in double quotes only when it contains leading or
+    /// trailing whitespace, starts with `"` or `'`, an embedded newline,
+    /// an embedded `=`, or a symbol the active `EscapePolicy` would
+    /// otherwise escape (`;`, `#`, `=`, `:`).
+    ///
+    /// This is the default: it keeps ordinary values unquoted while
+    /// quoting exactly the values that would otherwise be corrupted on
+    /// round-trip, regardless of which `EscapePolicy` is in effect.
+    WhenNeeded,
+    /// Always wrap values in double quotes.
+    Always,
+}
+
 /// Writing configuration
 pub struct WriteOption {
     /// Policies about how to escape characters
@@ -220,12 +455,17 @@ pub struct WriteOption {
 
     /// Newline style
     pub line_separator: LineSeparator,
+
+    /// When to wrap a value in double quotes on write, instead of relying
+    /// only on backslash escaping. See [`QuoteStyle`].
+    pub quote_style: QuoteStyle,
 }
 
 impl Default for WriteOption {
     fn default() -> WriteOption {
         WriteOption { escape_policy: EscapePolicy::Basics,
-                      line_separator: LineSeparator::SystemDefault }
+                      line_separator: LineSeparator::SystemDefault,
+                      quote_style: QuoteStyle::WhenNeeded }
     }
 }
 
@@ -279,6 +519,15 @@ type PropertiesImpl<K, V> = MultiMap<K, V>;
 type PropertiesImpl<K, V> = Vec<(K, V)>;
 
 /// Properties type (key-value pairs)
+///
+/// `Properties` holds only keys and values; it has no per-key metadata for
+/// trailing inline comments or blank-line spacing, and `write_to`/
+/// `write_to_opt` never emit anything beyond `key = value` lines. That
+/// capture-and-replay behavior is provided by [`LosslessIni`] instead,
+/// which models a document as an ordered sequence of events (including
+/// `Comment`/`Blank`) rather than attaching provenance to each key —
+/// round-trip this way if comments/spacing must survive a load-modify-save
+/// cycle.
 #[derive(Clone, Default, Debug, PartialEq)]
 pub struct Properties {
     data: PropertiesImpl<String, String>,
@@ -306,6 +555,120 @@ impl Properties {
     }
 }
 
+/// Error returned by the typed accessors on [`Properties`] and [`Ini`],
+/// distinguishing a missing key from one whose value could not be parsed.
+#[derive(Debug)]
+pub enum GetError {
+    /// The key is not present in this section.
+    NotPresent,
+    /// The key is present, but its value could not be parsed as the
+    /// requested type.
+    InvalidValue {
+        /// The raw, unparsed value.
+        value: String,
+        /// Human-readable reason parsing failed.
+        cause: String,
+    },
+}
+
+impl fmt::Display for GetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GetError::NotPresent => write!(f, "key is not present"),
+            GetError::InvalidValue { ref value, ref cause } => {
+                write!(f, "value `{}` could not be parsed: {}", value, cause)
+            }
+        }
+    }
+}
+
+impl error::Error for GetError {}
+
+// Split a trailing git-style multiplier suffix (k/K, m/M, g/G) off of a
+// numeric string, returning the remaining digits and the multiplier to
+// apply to their parsed value.
+fn split_int_suffix(raw: &str) -> (&str, u64) {
+    let trimmed = raw.trim();
+    match trimmed.chars().last() {
+        Some('k') | Some('K') => (trimmed[..trimmed.len() - 1].trim_end(), 1024),
+        Some('m') | Some('M') => (trimmed[..trimmed.len() - 1].trim_end(), 1024 * 1024),
+        Some('g') | Some('G') => (trimmed[..trimmed.len() - 1].trim_end(), 1024 * 1024 * 1024),
+        _ => (trimmed, 1),
+    }
+}
+
+fn parse_suffixed_i64(raw: &str) -> Result<i64, String> {
+    let (digits, multiplier) = split_int_suffix(raw);
+    let base: i64 = digits.parse().map_err(|e: std::num::ParseIntError| e.to_string())?;
+    base.checked_mul(multiplier as i64).ok_or_else(|| "integer overflow".to_owned())
+}
+
+fn parse_suffixed_u64(raw: &str) -> Result<u64, String> {
+    let (digits, multiplier) = split_int_suffix(raw);
+    let base: u64 = digits.parse().map_err(|e: std::num::ParseIntError| e.to_string())?;
+    base.checked_mul(multiplier).ok_or_else(|| "integer overflow".to_owned())
+}
+
+// Accepts the git/ini boolean vocabulary, case-insensitively; an empty
+// value (e.g. `key =`) is treated as `false`, matching git's convention
+// for a "presence" flag.
+fn parse_bool(raw: &str) -> Option<bool> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Some(false);
+    }
+    match trimmed.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+impl Properties {
+    /// Parse the first value associated with `key` using `T`'s `FromStr`
+    /// implementation.
+    pub fn get_parsed<T>(&self, key: &str) -> Result<T, GetError>
+        where T: FromStr,
+              T::Err: fmt::Display
+    {
+        let raw = self.get(key).ok_or(GetError::NotPresent)?;
+        raw.parse()
+           .map_err(|e: T::Err| GetError::InvalidValue { value: raw.clone(), cause: e.to_string() })
+    }
+
+    /// Parse the first value associated with `key` as a boolean.
+    ///
+    /// Accepts `true`/`yes`/`on`/`1` as `true` and `false`/`no`/`off`/`0`
+    /// (or an empty value) as `false`, case-insensitively.
+    pub fn get_bool(&self, key: &str) -> Result<bool, GetError> {
+        let raw = self.get(key).ok_or(GetError::NotPresent)?;
+        parse_bool(raw).ok_or_else(|| GetError::InvalidValue { value: raw.clone(),
+                                                                cause: "not a recognized boolean"
+                                                                    .to_owned() })
+    }
+
+    /// Parse the first value associated with `key` as a signed integer,
+    /// accepting an optional trailing `k`/`m`/`g` (or uppercase) multiplier
+    /// suffix, e.g. `"64M"` parses as `67108864`.
+    pub fn get_int(&self, key: &str) -> Result<i64, GetError> {
+        let raw = self.get(key).ok_or(GetError::NotPresent)?;
+        parse_suffixed_i64(raw).map_err(|cause| GetError::InvalidValue { value: raw.clone(), cause })
+    }
+
+    /// Like [`get_int`](Properties::get_int), but parses an unsigned
+    /// integer.
+    pub fn get_uint(&self, key: &str) -> Result<u64, GetError> {
+        let raw = self.get(key).ok_or(GetError::NotPresent)?;
+        parse_suffixed_u64(raw).map_err(|cause| GetError::InvalidValue { value: raw.clone(), cause })
+    }
+
+    /// Parse the first value associated with `key` as a floating point
+    /// number.
+    pub fn get_float(&self, key: &str) -> Result<f64, GetError> {
+        self.get_parsed(key)
+    }
+}
+
 #[cfg(not(feature = "preserve_order"))]
 impl Properties {
     /// Insert (key, value) pair
@@ -538,6 +901,38 @@ impl Ini {
         }
     }
 
+    /// Get the value from a section with key, parsed as a boolean. See
+    /// [`Properties::get_bool`].
+    pub fn get_bool_from<S>(&self, section: Option<S>, key: &str) -> Result<bool, GetError>
+        where S: Into<String>
+    {
+        self.section(section).ok_or(GetError::NotPresent)?.get_bool(key)
+    }
+
+    /// Get the value from a section with key, parsed as a signed integer.
+    /// See [`Properties::get_int`].
+    pub fn get_int_from<S>(&self, section: Option<S>, key: &str) -> Result<i64, GetError>
+        where S: Into<String>
+    {
+        self.section(section).ok_or(GetError::NotPresent)?.get_int(key)
+    }
+
+    /// Get the value from a section with key, parsed as an unsigned
+    /// integer. See [`Properties::get_uint`].
+    pub fn get_uint_from<S>(&self, section: Option<S>, key: &str) -> Result<u64, GetError>
+        where S: Into<String>
+    {
+        self.section(section).ok_or(GetError::NotPresent)?.get_uint(key)
+    }
+
+    /// Get the value from a section with key, parsed as a float. See
+    /// [`Properties::get_float`].
+    pub fn get_float_from<S>(&self, section: Option<S>, key: &str) -> Result<f64, GetError>
+        where S: Into<String>
+    {
+        self.section(section).ok_or(GetError::NotPresent)?.get_float(key)
+    }
+
     /// Delete a section, return the properties if it exists
     pub fn delete<S>(&mut self, section: Option<S>) -> Option<Properties>
         where S: Into<String>
@@ -635,7 +1030,7 @@ impl Ini {
         if let Some(props) = self.sections.get(&None) {
             for (k, v) in props.iter() {
                 let k_str = escape_str(&k[..], opt.escape_policy);
-                let v_str = escape_str(&v[..], opt.escape_policy);
+                let v_str = write_value_str(&v[..], &opt);
                 write!(writer, "{}={}{}", k_str, v_str, opt.line_separator)?;
             }
             firstline = false;
@@ -656,7 +1051,7 @@ impl Ini {
 
                 for (k, v) in props.iter() {
                     let k_str = escape_str(&k[..], opt.escape_policy);
-                    let v_str = escape_str(&v[..], opt.escape_policy);
+                    let v_str = write_value_str(&v[..], &opt);
                     write!(writer, "{}={}{}", k_str, v_str, opt.line_separator)?;
                 }
             }
@@ -680,8 +1075,45 @@ impl Ini {
 
     /// Load from a string with options
     pub fn load_from_str_opt(buf: &str, opt: ParseOption) -> Result<Ini, ParseError> {
+        let mut parser = Parser::new(buf.chars(), opt.clone());
+        let ini = parser.parse()?;
+
+        if opt.enable_includes {
+            let mut visited = Vec::new();
+            ini.resolve_includes(&opt, 0, &mut visited).map_err(|e| match e {
+                Error::Parse(e) => e,
+                Error::Io(e) => ParseError { line: 0,
+                                             col: 0,
+                                             msg: format!("failed to resolve include: {}", e) },
+            })
+        } else {
+            Ok(ini)
+        }
+    }
+
+    /// Load from a string, recovering from errors instead of failing on
+    /// the first one. Returns the `Ini` built from whatever parsed
+    /// successfully alongside every [`ParseError`] encountered along the
+    /// way; a fully valid input yields an empty error vector. Useful for
+    /// editors and config validators that want to report every problem
+    /// in a file in one pass. See [`Parser::parse_recover`].
+    pub fn load_from_str_recover(buf: &str) -> (Ini, Vec<ParseError>) {
+        Ini::load_from_str_recover_opt(buf, ParseOption::default())
+    }
+
+    /// Load from a string with options, recovering from errors. See
+    /// [`load_from_str_recover`](Ini::load_from_str_recover).
+    pub fn load_from_str_recover_opt(buf: &str, opt: ParseOption) -> (Ini, Vec<ParseError>) {
         let mut parser = Parser::new(buf.chars(), opt);
-        parser.parse()
+        parser.parse_recover()
+    }
+
+    /// Load from a string in lossless mode: the returned [`LosslessIni`]
+    /// can be written back out with comments, blank lines, and original
+    /// formatting preserved. See [`LosslessIni`] for details and
+    /// limitations.
+    pub fn load_from_str_lossless(buf: &str) -> Result<LosslessIni, ParseError> {
+        LosslessIni::parse(buf)
     }
 
     /// Load from a reader
@@ -700,10 +1132,17 @@ impl Ini {
     pub fn read_from_opt<R: Read>(reader: &mut R, opt: ParseOption) -> Result<Ini, Error> {
         let mut s = String::new();
         reader.read_to_string(&mut s).map_err(Error::Io)?;
-        let mut parser = Parser::new(s.chars(), opt);
-        match parser.parse() {
-            Err(e) => Err(Error::Parse(e)),
-            Ok(success) => Ok(success),
+        let mut parser = Parser::new(s.chars(), opt.clone());
+        let ini = match parser.parse() {
+            Err(e) => return Err(Error::Parse(e)),
+            Ok(success) => success,
+        };
+
+        if opt.enable_includes {
+            let mut visited = Vec::new();
+            ini.resolve_includes(&opt, 0, &mut visited)
+        } else {
+            Ok(ini)
         }
     }
 
@@ -727,10 +1166,230 @@ impl Ini {
             }
             Ok(r) => r,
         };
+
+        let opt = if opt.enable_includes && opt.include_base_dir.is_none() {
+            let base_dir = filename.as_ref().parent().map(|p| p.to_path_buf());
+            ParseOption { include_base_dir: base_dir,
+                          ..opt }
+        } else {
+            opt
+        };
+
         Ini::read_from_opt(&mut reader, opt)
     }
 }
 
+impl Ini {
+    // Resolve `[include]` / `[includeIf "..."]` directives found anywhere
+    // in `self`, merging each referenced file in and removing the
+    // directive section itself. `visited` tracks canonicalized paths
+    // already on the include stack, to detect cycles.
+    fn resolve_includes(mut self,
+                         opt: &ParseOption,
+                         depth: usize,
+                         visited: &mut Vec<PathBuf>)
+                         -> Result<Ini, Error> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(Error::Parse(ParseError { line: 0,
+                                                 col: 0,
+                                                 msg: format!("include depth exceeded {} levels",
+                                                              MAX_INCLUDE_DEPTH) }));
+        }
+
+        let mut include_paths: Vec<String> = Vec::new();
+        let mut directive_sections: Vec<Option<String>> = Vec::new();
+
+        for (name, props) in self.sections.iter() {
+            let included = match name {
+                Some(n) if n == "include" => true,
+                Some(n) => match include_if_condition("include", n) {
+                    Some(cond) => include_if_matches(cond),
+                    None => false,
+                },
+                None => false,
+            };
+
+            if included {
+                if let Some(paths) = props.get_vec("path") {
+                    include_paths.extend(paths.into_iter().cloned());
+                }
+                directive_sections.push(name.clone());
+            }
+        }
+
+        for name in directive_sections {
+            self.sections.remove(&name);
+        }
+
+        for rel_path in include_paths {
+            let base_dir = opt.include_base_dir.as_ref().ok_or_else(|| {
+                Error::Parse(ParseError { line: 0,
+                                          col: 0,
+                                          msg: "include directive requires include_base_dir \
+                                                to resolve a relative path"
+                                              .to_owned() })
+            })?;
+
+            let full_path = base_dir.join(&rel_path);
+            let canonical = full_path.canonicalize().map_err(|e| {
+                Error::Parse(ParseError { line: 0,
+                                          col: 0,
+                                          msg: format!("include directive references missing \
+                                                        path {}: {}",
+                                                       full_path.display(),
+                                                       e) })
+            })?;
+
+            if visited.contains(&canonical) {
+                return Err(Error::Parse(ParseError { line: 0,
+                                                     col: 0,
+                                                     msg: format!("include cycle detected: {}",
+                                                                  canonical.display()) }));
+            }
+
+            let included_opt = ParseOption { include_base_dir:
+                                                  full_path.parent().map(|p| p.to_path_buf()),
+                                              ..opt.clone() };
+
+            let mut file = File::open(&full_path).map_err(Error::Io)?;
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).map_err(Error::Io)?;
+
+            let mut parser = Parser::new(contents.chars(), included_opt.clone());
+            let included = parser.parse().map_err(Error::Parse)?;
+
+            visited.push(canonical);
+            let included = included.resolve_includes(&included_opt, depth + 1, visited)?;
+            visited.pop();
+
+            self.merge_from(included);
+        }
+
+        Ok(self)
+    }
+
+    // Merge `other` into `self`, with `other`'s keys overriding same-named
+    // keys already present (matching top-down, later-wins evaluation).
+    fn merge_from(&mut self, other: Ini) {
+        for (name, props) in other.sections {
+            let target = self.sections.entry(name).or_insert_with(Default::default);
+            // Clear out any value(s) `target` already has for a key
+            // before laying down `other`'s, so `other` actually
+            // overrides rather than merely appending (which `get`/
+            // `get_vec` would still resolve to the pre-existing value).
+            // Only the first of possibly-repeated occurrences of `k` in
+            // `other` needs to trigger the clear.
+            let mut overridden: HashSet<String> = HashSet::new();
+            for (k, v) in props.iter() {
+                if overridden.insert(k.clone()) {
+                    target.remove(k);
+                }
+                target.insert(k.clone(), v.clone());
+            }
+        }
+    }
+
+    /// Load `filename`, resolving include directives per `opt` instead of
+    /// via [`ParseOption::enable_includes`]. See [`IncludeOption`] for why
+    /// this alternate entry point exists.
+    pub fn load_from_file_with_includes<P: AsRef<Path>>(filename: P,
+                                                         mut opt: IncludeOption)
+                                                         -> Result<Ini, Error> {
+        let filename = filename.as_ref();
+        if opt.base_dir.is_none() {
+            opt.base_dir = filename.parent().map(|p| p.to_path_buf());
+        }
+
+        let ini = Ini::load_from_file(filename)?;
+        let mut visited = Vec::new();
+        ini.resolve_includes_with(&opt, 0, &mut visited)
+    }
+
+    // Like `resolve_includes`, but driven by an `IncludeOption` (runtime
+    // directive name and depth) instead of the fixed "include" directive
+    // and `MAX_INCLUDE_DEPTH` that `ParseOption::enable_includes` uses.
+    // Kept as a separate method rather than folded into `resolve_includes`
+    // since the two entry points take differently-shaped configuration;
+    // the cycle-detection and section-merge logic is otherwise identical.
+    fn resolve_includes_with(mut self,
+                             opt: &IncludeOption,
+                             depth: usize,
+                             visited: &mut Vec<PathBuf>)
+                             -> Result<Ini, Error> {
+        if depth > opt.max_depth {
+            return Err(Error::Parse(ParseError { line: 0,
+                                                 col: 0,
+                                                 msg: format!("include depth exceeded {} levels",
+                                                              opt.max_depth) }));
+        }
+
+        let mut include_paths: Vec<String> = Vec::new();
+        let mut directive_sections: Vec<Option<String>> = Vec::new();
+
+        for (name, props) in self.sections.iter() {
+            let included = match name {
+                Some(n) if n == &opt.directive => true,
+                Some(n) => match include_if_condition(&opt.directive, n) {
+                    Some(cond) => include_if_matches(cond),
+                    None => false,
+                },
+                None => false,
+            };
+
+            if included {
+                if let Some(paths) = props.get_vec("path") {
+                    include_paths.extend(paths.into_iter().cloned());
+                }
+                directive_sections.push(name.clone());
+            }
+        }
+
+        for name in directive_sections {
+            self.sections.remove(&name);
+        }
+
+        for rel_path in include_paths {
+            let base_dir = opt.base_dir.as_ref().ok_or_else(|| {
+                Error::Parse(ParseError { line: 0,
+                                          col: 0,
+                                          msg: format!("{} directive requires a base directory \
+                                                        to resolve a relative path",
+                                                       opt.directive) })
+            })?;
+
+            let full_path = base_dir.join(&rel_path);
+            let canonical = full_path.canonicalize().map_err(|e| {
+                Error::Parse(ParseError { line: 0,
+                                          col: 0,
+                                          msg: format!("{} directive references missing path {}: {}",
+                                                       opt.directive,
+                                                       full_path.display(),
+                                                       e) })
+            })?;
+
+            if visited.contains(&canonical) {
+                return Err(Error::Parse(ParseError { line: 0,
+                                                     col: 0,
+                                                     msg: format!("include cycle detected: {}",
+                                                                  canonical.display()) }));
+            }
+
+            let included_opt = IncludeOption { base_dir: full_path.parent().map(|p| p.to_path_buf()),
+                                               ..opt.clone() };
+
+            let included = Ini::load_from_file(&full_path)?;
+
+            visited.push(canonical);
+            let included = included.resolve_includes_with(&included_opt, depth + 1, visited)?;
+            visited.pop();
+
+            self.merge_from(included);
+        }
+
+        Ok(self)
+    }
+}
+
 /// Iterator for sections
 pub struct SectionIterator<'a> {
     mapiter: Iter<'a, Option<String>, Properties>,
@@ -815,6 +1474,164 @@ impl IntoIterator for Ini {
     }
 }
 
+/// A single layer loaded into a [`ConfigSet`]: the parsed `Ini`, the path
+/// it came from, and its raw text (kept around to answer
+/// `get_with_origin`'s line-number queries without re-reading the file).
+struct ConfigLayer {
+    path: PathBuf,
+    raw: String,
+    ini: Ini,
+}
+
+/// The value returned by [`ConfigSet::get_with_origin`]: the effective
+/// value together with where it came from.
+#[derive(Debug, Clone, Copy)]
+pub struct ValueOrigin<'a> {
+    /// The effective value, from the highest-priority layer that set it.
+    pub value: &'a str,
+    /// The file that last set this value.
+    pub path: &'a Path,
+    /// The 1-indexed line in `path` the value was set on, if it could be
+    /// located. This is a best-effort textual lookup, not data the parser
+    /// tracks structurally, so it can be `None` for values produced by
+    /// escape sequences that change a key's apparent spelling.
+    pub line: Option<usize>,
+}
+
+// Best-effort textual lookup of the line a `key = value` line appears on
+// within `section`, used only for `ConfigSet`'s debugging-oriented origin
+// queries (the main parser does not track source positions per-key).
+fn find_line(raw: &str, section: Option<&str>, key: &str) -> Option<usize> {
+    let mut current_section: Option<String> = None;
+
+    for (idx, line) in raw.lines().enumerate() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            current_section = Some(trimmed[1..trimmed.len() - 1].trim().to_owned());
+            continue;
+        }
+
+        if current_section.as_deref() != section {
+            continue;
+        }
+
+        if let Some(sep) = trimmed.find(|c| c == '=' || c == ':') {
+            if trimmed[..sep].trim() == key {
+                return Some(idx + 1);
+            }
+        }
+    }
+
+    None
+}
+
+/// A cascading stack of INI files, in the style of git's or Mercurial's
+/// layered configuration: a system file, then a user file, then a local
+/// file, with later layers overriding same-named values in earlier ones.
+///
+/// Unlike loading and merging `Ini`s by hand, a `ConfigSet` remembers which
+/// file (and, best-effort, which line) each effective value came from, so
+/// callers can answer "why is this value set this way".
+pub struct ConfigSet {
+    layers: Vec<ConfigLayer>,
+}
+
+impl ConfigSet {
+    /// Create an empty `ConfigSet`.
+    pub fn new() -> ConfigSet {
+        ConfigSet { layers: Vec::new() }
+    }
+
+    /// Load `path` as a new, highest-priority layer.
+    pub fn load_path<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut raw = String::new();
+        File::open(&path).and_then(|mut f| f.read_to_string(&mut raw)).map_err(Error::Io)?;
+
+        let ini = Ini::load_from_str(&raw).map_err(Error::Parse)?;
+        self.layers.push(ConfigLayer { path, raw, ini });
+        Ok(())
+    }
+
+    fn load_path_if_exists<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        if path.as_ref().exists() {
+            self.load_path(path)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Load the system-wide config for `app_name`, if present.
+    ///
+    /// On Unix this is `/etc/<app_name>/config.ini`; on Windows it is
+    /// `%PROGRAMDATA%\<app_name>\config.ini`.
+    pub fn load_system(&mut self, app_name: &str) -> Result<(), Error> {
+        #[cfg(windows)]
+        let base = PathBuf::from(std::env::var("PROGRAMDATA")
+            .unwrap_or_else(|_| "C:\\ProgramData".to_owned()));
+        #[cfg(not(windows))]
+        let base = PathBuf::from("/etc");
+
+        self.load_path_if_exists(base.join(app_name).join("config.ini"))
+    }
+
+    /// Load the current user's config for `app_name`, if present.
+    ///
+    /// This is `$HOME/.<app_name>.ini` on Unix and
+    /// `%USERPROFILE%\<app_name>.ini` on Windows.
+    pub fn load_user(&mut self, app_name: &str) -> Result<(), Error> {
+        let home = match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+            Some(home) => PathBuf::from(home),
+            None => return Ok(()),
+        };
+
+        #[cfg(windows)]
+        let path = home.join(format!("{}.ini", app_name));
+        #[cfg(not(windows))]
+        let path = home.join(format!(".{}.ini", app_name));
+
+        self.load_path_if_exists(path)
+    }
+
+    /// Get the highest-priority value for `section`/`key` across all
+    /// loaded layers.
+    pub fn get(&self, section: Option<&str>, key: &str) -> Option<&str> {
+        self.layers.iter().rev().find_map(|layer| layer.ini.get_from(section, key))
+    }
+
+    /// Like [`get`](ConfigSet::get), but also reports which file (and,
+    /// best-effort, which line) the effective value came from.
+    pub fn get_with_origin(&self, section: Option<&str>, key: &str) -> Option<ValueOrigin> {
+        self.layers.iter().rev().find_map(|layer| {
+            layer.ini.get_from(section, key).map(|value| {
+                ValueOrigin { value,
+                              path: layer.path.as_path(),
+                              line: find_line(&layer.raw, section, key) }
+            })
+        })
+    }
+
+    /// Iterate, in load order, over the paths of every layer that sets
+    /// `section`/`key` -- not just the one that currently wins -- useful
+    /// for answering "why is this value set".
+    pub fn layers_for<'a>(&'a self,
+                          section: Option<&'a str>,
+                          key: &'a str)
+                          -> impl Iterator<Item = &'a Path> + 'a {
+        self.layers
+            .iter()
+            .filter(move |layer| layer.ini.get_from(section, key).is_some())
+            .map(|layer| layer.path.as_path())
+    }
+}
+
+impl Default for ConfigSet {
+    fn default() -> ConfigSet {
+        ConfigSet::new()
+    }
+}
+
 // Ini parser
 struct Parser<'a> {
     ch: Option<char>,
@@ -864,43 +1681,1721 @@ impl error::Error for Error {
     }
 }
 
-impl<'a> Parser<'a> {
-    // Create a parser
-    pub fn new(rdr: Chars<'a>, opt: ParseOption) -> Parser<'a> {
-        let mut p = Parser { ch: None,
-                             line: 0,
-                             col: 0,
-                             rdr,
-                             opt };
-        p.bump();
-        p
+/// A stable `extern "C"` surface for loading and querying INI files from
+/// non-Rust hosts.
+///
+/// Every function in this module is safe to call from C: errors are
+/// marshalled into a caller-owned, UTF-8, NUL-terminated buffer via an
+/// `err_out` out-pointer instead of panicking across the FFI boundary, and
+/// every string this module hands back is an owned copy the caller must
+/// release with [`ini_string_free`](capi::ini_string_free).
+#[cfg(feature = "capi")]
+pub mod capi {
+    use super::*;
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_int};
+    use std::ptr;
+
+    // Marshal `msg` into `*err_out` as an owned C string, if the caller
+    // provided a slot for it.
+    unsafe fn set_error(err_out: *mut *mut c_char, msg: &str) {
+        if err_out.is_null() {
+            return;
+        }
+        let c_msg = CString::new(msg).unwrap_or_else(|_| CString::new("invalid error message").unwrap());
+        *err_out = c_msg.into_raw();
     }
 
-    fn eof(&self) -> bool {
-        self.ch.is_none()
+    unsafe fn str_from_c<'a>(s: *const c_char) -> Option<&'a str> {
+        if s.is_null() {
+            None
+        } else {
+            CStr::from_ptr(s).to_str().ok()
+        }
     }
 
-    fn bump(&mut self) {
-        self.ch = self.rdr.next();
-        match self.ch {
-            Some('\n') => {
-                self.line += 1;
-                self.col = 0;
+    /// Load an `Ini` from a file path. Returns null on failure, with a
+    /// description written to `*err_out` (if `err_out` is non-null).
+    ///
+    /// # Safety
+    ///
+    /// `path` must be a valid, NUL-terminated C string. `err_out`, if
+    /// non-null, must point to writable memory for a `*mut c_char`.
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_load_path(path: *const c_char,
+                                           err_out: *mut *mut c_char)
+                                           -> *mut Ini {
+        let path = match str_from_c(path) {
+            Some(p) => p,
+            None => {
+                set_error(err_out, "path is not valid UTF-8");
+                return ptr::null_mut();
             }
-            Some(..) => {
-                self.col += 1;
+        };
+
+        match Ini::load_from_file(path) {
+            Ok(ini) => Box::into_raw(Box::new(ini)),
+            Err(e) => {
+                set_error(err_out, &e.to_string());
+                ptr::null_mut()
             }
-            None => {}
         }
     }
 
-    fn error<U, M: Into<String>>(&self, msg: M) -> Result<U, ParseError> {
-        Err(ParseError { line: self.line,
-                         col: self.col,
-                         msg: msg.into() })
-    }
+    /// Get the value for `section`/`key`, or null if absent. `section`
+    /// may be null for the general section.
+    ///
+    /// # Safety
+    ///
+    /// `ini` must be a valid pointer returned by
+    /// [`ini_load_path`]. `section` (if non-null) and `key` must be valid
+    /// NUL-terminated C strings.
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_get(ini: *const Ini,
+                                     section: *const c_char,
+                                     key: *const c_char)
+                                     -> *mut c_char {
+        let ini = match ini.as_ref() {
+            Some(ini) => ini,
+            None => return ptr::null_mut(),
+        };
+        let key = match str_from_c(key) {
+            Some(k) => k,
+            None => return ptr::null_mut(),
+        };
+        let section = if section.is_null() { None } else { str_from_c(section) };
 
-    /// Consume all the white space until the end of the line or a tab
+        match ini.get_from(section, key) {
+            Some(value) => match CString::new(value) {
+                Ok(c) => c.into_raw(),
+                Err(_) => ptr::null_mut(),
+            },
+            None => ptr::null_mut(),
+        }
+    }
+
+    /// Number of sections in `ini`, including the general section if it
+    /// has any keys.
+    ///
+    /// # Safety
+    ///
+    /// `ini` must be a valid pointer returned by [`ini_load_path`].
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_section_count(ini: *const Ini) -> usize {
+        match ini.as_ref() {
+            Some(ini) => ini.sections.len(),
+            None => 0,
+        }
+    }
+
+    /// Write `ini` out to `path`. Returns 0 on success, non-zero
+    /// (with a description in `*err_out`) on failure.
+    ///
+    /// # Safety
+    ///
+    /// `ini` must be a valid pointer returned by [`ini_load_path`]. `path`
+    /// must be a valid NUL-terminated C string.
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_write_path(ini: *const Ini,
+                                            path: *const c_char,
+                                            err_out: *mut *mut c_char)
+                                            -> c_int {
+        let ini = match ini.as_ref() {
+            Some(ini) => ini,
+            None => {
+                set_error(err_out, "ini is null");
+                return -1;
+            }
+        };
+        let path = match str_from_c(path) {
+            Some(p) => p,
+            None => {
+                set_error(err_out, "path is not valid UTF-8");
+                return -1;
+            }
+        };
+
+        match ini.write_to_file(path) {
+            Ok(()) => 0,
+            Err(e) => {
+                set_error(err_out, &e.to_string());
+                -1
+            }
+        }
+    }
+
+    /// Free an `Ini` previously returned by [`ini_load_path`].
+    ///
+    /// # Safety
+    ///
+    /// `ini` must either be null or a pointer previously returned by
+    /// [`ini_load_path`], not already freed.
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_free(ini: *mut Ini) {
+        if !ini.is_null() {
+            drop(Box::from_raw(ini));
+        }
+    }
+
+    /// Free a string previously returned by this module (from
+    /// [`ini_get`] or an error out-pointer).
+    ///
+    /// # Safety
+    ///
+    /// `s` must either be null or a pointer previously returned by this
+    /// module, not already freed.
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_string_free(s: *mut c_char) {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    }
+
+    /// Opaque iterator over the section names of an `Ini`.
+    pub struct IniSectionIter {
+        names: std::vec::IntoIter<Option<String>>,
+    }
+
+    /// Create a new iterator over `ini`'s section names, in iteration
+    /// order. The general section (if present) yields null.
+    ///
+    /// # Safety
+    ///
+    /// `ini` must be a valid pointer returned by [`ini_load_path`].
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_sections_new(ini: *const Ini) -> *mut IniSectionIter {
+        let ini = match ini.as_ref() {
+            Some(ini) => ini,
+            None => return ptr::null_mut(),
+        };
+        let names: Vec<Option<String>> = ini.sections.keys().cloned().collect();
+        Box::into_raw(Box::new(IniSectionIter { names: names.into_iter() }))
+    }
+
+    /// Advance the iterator, returning the next section name (owned,
+    /// caller-freed) or null for the general section or end-of-iteration.
+    /// Use [`ini_sections_has_next`] to distinguish "general section" from
+    /// "done".
+    ///
+    /// # Safety
+    ///
+    /// `iter` must be a valid pointer returned by [`ini_sections_new`].
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_sections_next(iter: *mut IniSectionIter) -> *mut c_char {
+        let iter = match iter.as_mut() {
+            Some(iter) => iter,
+            None => return ptr::null_mut(),
+        };
+        match iter.names.next() {
+            Some(Some(name)) => CString::new(name).map(CString::into_raw).unwrap_or(ptr::null_mut()),
+            Some(None) => ptr::null_mut(),
+            None => ptr::null_mut(),
+        }
+    }
+
+    /// True if the iterator has another section to yield.
+    ///
+    /// # Safety
+    ///
+    /// `iter` must be a valid pointer returned by [`ini_sections_new`].
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_sections_has_next(iter: *const IniSectionIter) -> bool {
+        match iter.as_ref() {
+            Some(iter) => iter.names.len() > 0,
+            None => false,
+        }
+    }
+
+    /// Free an iterator previously returned by [`ini_sections_new`].
+    ///
+    /// # Safety
+    ///
+    /// `iter` must either be null or a pointer previously returned by
+    /// [`ini_sections_new`], not already freed.
+    #[no_mangle]
+    pub unsafe extern "C" fn ini_sections_free(iter: *mut IniSectionIter) {
+        if !iter.is_null() {
+            drop(Box::from_raw(iter));
+        }
+    }
+}
+
+/// A single parsed line in a [`LosslessIni`] document.
+///
+/// Every variant keeps enough of the original text to be re-emitted
+/// byte-for-byte by [`LosslessIni::write_to`], except for `KeyValue`
+/// entries whose value was changed through [`LosslessIni::set`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A `[section]` line, kept verbatim in `raw`.
+    SectionHeader {
+        /// The line's exact original text (without its line terminator).
+        raw: String,
+        /// The section name, trimmed of surrounding whitespace.
+        name: String,
+    },
+    /// A `key = value` (or `key: value`) line.
+    KeyValue {
+        /// The section this key belongs to, as of this point in the file.
+        section: Option<String>,
+        /// Everything before the delimiter, verbatim (the key plus any
+        /// surrounding whitespace).
+        prefix: String,
+        /// The key, trimmed of surrounding whitespace.
+        key: String,
+        /// The delimiter character used (`=` or `:`).
+        delimiter: char,
+        /// Everything after the delimiter, verbatim, including
+        /// whitespace and (if present) surrounding quotes.
+        raw_value: String,
+        /// The value with surrounding whitespace trimmed and a single
+        /// layer of matching quotes stripped, for convenient reading.
+        value: String,
+    },
+    /// A comment line (`;` or `#`), kept verbatim in `raw`.
+    Comment {
+        /// The line's exact original text (without its line terminator).
+        raw: String,
+    },
+    /// A blank (or whitespace-only) line, kept verbatim in `raw`.
+    Blank {
+        /// The line's exact original text (without its line terminator).
+        raw: String,
+    },
+}
+
+fn strip_matching_quotes(s: &str) -> String {
+    let bytes = s.as_bytes();
+    if bytes.len() >= 2 {
+        let first = bytes[0];
+        let last = bytes[bytes.len() - 1];
+        if (first == b'"' || first == b'\'') && first == last {
+            return s[1..s.len() - 1].to_owned();
+        }
+    }
+    s.to_owned()
+}
+
+// Split `input` into (line content without terminator, exact terminator)
+// pairs, so every byte of `input` can be reproduced by rejoining them.
+fn split_raw_lines(input: &str) -> Vec<(String, String)> {
+    let mut lines = Vec::new();
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        match rest.find('\n') {
+            Some(idx) => {
+                let line = &rest[..idx];
+                if let Some(stripped) = line.strip_suffix('\r') {
+                    lines.push((stripped.to_owned(), "\r\n".to_owned()));
+                } else {
+                    lines.push((line.to_owned(), "\n".to_owned()));
+                }
+                rest = &rest[idx + 1..];
+            }
+            None => {
+                lines.push((rest.to_owned(), String::new()));
+                rest = "";
+            }
+        }
+    }
+
+    lines
+}
+
+/// A parsed INI document that preserves everything a plain [`Ini`] would
+/// discard -- comments, blank lines, the choice of `=` vs `:`, and
+/// surrounding whitespace -- so it can be read, have a handful of values
+/// changed, and written back out with the rest of the file untouched.
+///
+/// This is a distinct, simpler parser from [`Parser`]: it works line by
+/// line rather than character by character, and so (unlike the main
+/// `Ini` parser) does not support values that span multiple physical
+/// lines via quoting, or backslash escape sequences within a value.
+#[derive(Debug, Clone)]
+pub struct LosslessIni {
+    events: Vec<(Event, String)>,
+}
+
+impl LosslessIni {
+    /// Parse `input` into a lossless document.
+    ///
+    /// The key invariant this upholds: for any `input` this accepts,
+    /// `LosslessIni::parse(input).unwrap()` then
+    /// [`write_to`](LosslessIni::write_to) into a buffer yields exactly
+    /// `input` back, provided no value was changed via [`set`](LosslessIni::set)
+    /// in between.
+    pub fn parse(input: &str) -> Result<LosslessIni, ParseError> {
+        let mut events = Vec::new();
+        let mut current_section: Option<String> = None;
+
+        for (line_no, (content, terminator)) in split_raw_lines(input).into_iter().enumerate() {
+            let trimmed = content.trim();
+
+            let event = if trimmed.is_empty() {
+                Event::Blank { raw: content }
+            } else if trimmed.starts_with(';') || trimmed.starts_with('#') {
+                Event::Comment { raw: content }
+            } else if trimmed.starts_with('[') {
+                match content.find(']') {
+                    Some(end) => {
+                        let start = content.find('[').unwrap();
+                        let name = content[start + 1..end].trim().to_owned();
+                        current_section = Some(name.clone());
+                        Event::SectionHeader { raw: content, name }
+                    }
+                    None => {
+                        return Err(ParseError { line: line_no + 1,
+                                                col: content.len(),
+                                                msg: "unterminated section header".to_owned() });
+                    }
+                }
+            } else {
+                match content.find(|c| c == '=' || c == ':') {
+                    Some(pos) => {
+                        let prefix = content[..pos].to_owned();
+                        let key = prefix.trim().to_owned();
+                        if key.is_empty() {
+                            return Err(ParseError { line: line_no + 1,
+                                                    col: pos,
+                                                    msg: "missing key".to_owned() });
+                        }
+                        let delimiter = content[pos..].chars().next().unwrap();
+                        let raw_value = content[pos + delimiter.len_utf8()..].to_owned();
+                        let value = strip_matching_quotes(raw_value.trim());
+                        Event::KeyValue { section: current_section.clone(),
+                                         prefix,
+                                         key,
+                                         delimiter,
+                                         raw_value,
+                                         value }
+                    }
+                    None => {
+                        return Err(ParseError { line: line_no + 1,
+                                                col: content.len(),
+                                                msg: "expecting '=' or ':'".to_owned() });
+                    }
+                }
+            };
+
+            events.push((event, terminator));
+        }
+
+        Ok(LosslessIni { events })
+    }
+
+    /// Write the document back out. If no value has been changed since
+    /// parsing, this reproduces the original input byte-for-byte.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (event, terminator) in &self.events {
+            match event {
+                Event::SectionHeader { raw, .. }
+                | Event::Comment { raw }
+                | Event::Blank { raw } => write!(writer, "{}{}", raw, terminator)?,
+                Event::KeyValue { prefix, delimiter, raw_value, .. } => {
+                    write!(writer, "{}{}{}{}", prefix, delimiter, raw_value, terminator)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the current value for `section`/`key`, if present.
+    pub fn get(&self, section: Option<&str>, key: &str) -> Option<&str> {
+        self.events.iter().find_map(|(event, _)| match event {
+            Event::KeyValue { section: s, key: k, value, .. }
+                if s.as_deref() == section && k == key =>
+            {
+                Some(value.as_str())
+            }
+            _ => None,
+        })
+    }
+
+    /// Change the value for an existing `section`/`key`, keeping every
+    /// other byte of the document untouched. Returns `false` (without
+    /// modifying anything) if the key does not already exist; this mode
+    /// does not support inserting new keys.
+    pub fn set(&mut self, section: Option<&str>, key: &str, value: &str) -> bool {
+        for (event, _) in &mut self.events {
+            if let Event::KeyValue { section: s, key: k, raw_value, value: v, .. } = event {
+                if s.as_deref() == section && k == key {
+                    // Keep the original leading whitespace between the
+                    // delimiter and the value, so a simple re-assignment
+                    // doesn't also reformat the line.
+                    let leading_ws_len = raw_value.len() - raw_value.trim_start().len();
+                    let leading_ws = raw_value[..leading_ws_len].to_owned();
+                    *raw_value = format!("{}{}", leading_ws, value);
+                    *v = value.to_owned();
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Remove an existing `section`/`key`, deleting its entire line
+    /// (including its original line terminator) and keeping every other
+    /// byte of the document untouched. Returns `false` (without modifying
+    /// anything) if the key does not exist.
+    pub fn remove(&mut self, section: Option<&str>, key: &str) -> bool {
+        let pos = self.events.iter().position(|(event, _)| match event {
+            Event::KeyValue { section: s, key: k, .. } => s.as_deref() == section && k == key,
+            _ => false,
+        });
+
+        match pos {
+            Some(pos) => {
+                self.events.remove(pos);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Serde support: map an `Ini`'s sections and properties onto typed
+/// structs, and back.
+///
+/// A top-level field named like a section maps to that `[section]`; a
+/// nested struct or map deserializes/serializes that section's
+/// key/value pairs. Scalar top-level fields (not matching any section
+/// name, or more precisely any field whose value is not itself a
+/// struct/map) are read from and written to the general (`None`)
+/// section. `Vec<String>` fields round-trip through repeated keys via
+/// [`Properties::get_vec`] on the way in, and repeated `insert` calls on
+/// the way out.
+///
+/// Values are handed to serde as strings on the way in: `String` fields
+/// deserialize, while `bool` and numeric fields are rejected with an
+/// [`Error`](enum@Error) rather than parsed from their text. On the way
+/// out, such fields are written as their text form.
+///
+/// This only covers the shapes described above: scalars, nested
+/// structs/maps one level deep, and `Vec<String>`. Anything else (enums,
+/// tuples, nested sequences of non-strings, ...) is rejected with an
+/// [`Error`](enum@Error).
+#[cfg(feature = "serde")]
+pub mod serde_support {
+    use super::*;
+    use serde::de::{self, Deserialize, IntoDeserializer, MapAccess, Visitor};
+    use serde::ser::{self, Serialize, SerializeMap, SerializeStruct};
+
+    /// Error produced while deserializing from, or serializing to, an
+    /// [`Ini`](super::Ini).
+    #[derive(Debug)]
+    pub struct Error(String);
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl error::Error for Error {}
+
+    impl de::Error for Error {
+        fn custom<T: fmt::Display>(msg: T) -> Self {
+            Error(msg.to_string())
+        }
+    }
+
+    impl ser::Error for Error {
+        fn custom<T: fmt::Display>(msg: T) -> Self {
+            Error(msg.to_string())
+        }
+    }
+
+    /// Deserialize a `T` from an already-parsed [`Ini`](super::Ini).
+    pub fn from_ini<'de, T: Deserialize<'de>>(ini: &Ini) -> Result<T, Error> {
+        T::deserialize(IniDeserializer { ini })
+    }
+
+    /// Parse `s` as INI and deserialize it into a `T` in one step.
+    pub fn from_str<'de, T: Deserialize<'de>>(s: &str) -> Result<T, Error> {
+        let ini = Ini::load_from_str(s).map_err(|e| Error(e.to_string()))?;
+        from_ini(&ini)
+    }
+
+    /// Serialize `value` into a fresh [`Ini`](super::Ini).
+    pub fn to_ini<T: Serialize>(value: &T) -> Result<Ini, Error> {
+        let mut ini = Ini::new();
+        value.serialize(TopSerializer { ini: &mut ini })?;
+        Ok(ini)
+    }
+
+    /// Serialize `value` to its INI text representation in one step.
+    pub fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
+        let ini = to_ini(value)?;
+        let mut buf = Vec::new();
+        ini.write_to(&mut buf).map_err(|e| Error(e.to_string()))?;
+        String::from_utf8(buf).map_err(|e| Error(e.to_string()))
+    }
+
+    // ---- Deserializer ----
+
+    struct IniDeserializer<'a> {
+        ini: &'a Ini,
+    }
+
+    impl<'de, 'a> de::Deserializer<'de> for IniDeserializer<'a> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            self.deserialize_map(visitor)
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(self,
+                                                _name: &'static str,
+                                                _fields: &'static [&'static str],
+                                                visitor: V)
+                                                -> Result<V::Value, Error> {
+            self.deserialize_map(visitor)
+        }
+
+        fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let mut seen = std::collections::HashSet::new();
+            let general: Vec<(&Properties, &str)> = match self.ini.section(None::<String>) {
+                Some(props) => props.iter()
+                                    .map(|(k, _)| k.as_str())
+                                    .filter(|k| seen.insert(*k))
+                                    .map(|k| (props, k))
+                                    .collect(),
+                None => Vec::new(),
+            };
+            let sections: Vec<(&str, &Properties)> =
+                self.ini
+                    .sections
+                    .iter()
+                    .filter_map(|(name, props)| name.as_deref().map(|name| (name, props)))
+                    .collect();
+            visitor.visit_map(TopMapAccess { general: general.into_iter(),
+                                              sections: sections.into_iter(),
+                                              value: TopValue::None })
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct enum identifier ignored_any
+        }
+    }
+
+    enum TopValue<'a> {
+        None,
+        Scalar(&'a Properties, &'a str),
+        Section(&'a Properties),
+    }
+
+    struct TopMapAccess<'a> {
+        general: std::vec::IntoIter<(&'a Properties, &'a str)>,
+        sections: std::vec::IntoIter<(&'a str, &'a Properties)>,
+        value: TopValue<'a>,
+    }
+
+    impl<'de, 'a> MapAccess<'de> for TopMapAccess<'a> {
+        type Error = Error;
+
+        fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self,
+                                                       seed: K)
+                                                       -> Result<Option<K::Value>, Error> {
+            if let Some((props, k)) = self.general.next() {
+                self.value = TopValue::Scalar(props, k);
+                return seed.deserialize(k.into_deserializer()).map(Some);
+            }
+            if let Some((name, props)) = self.sections.next() {
+                self.value = TopValue::Section(props);
+                return seed.deserialize(name.into_deserializer()).map(Some);
+            }
+            Ok(None)
+        }
+
+        fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+            match std::mem::replace(&mut self.value, TopValue::None) {
+                TopValue::Scalar(props, key) => seed.deserialize(PropertyValueDeserializer { props, key }),
+                TopValue::Section(props) => seed.deserialize(SectionDeserializer { props }),
+                TopValue::None => Err(de::Error::custom("value requested before key")),
+            }
+        }
+    }
+
+    struct SectionDeserializer<'a> {
+        props: &'a Properties,
+    }
+
+    impl<'de, 'a> de::Deserializer<'de> for SectionDeserializer<'a> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            self.deserialize_map(visitor)
+        }
+
+        fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let mut seen = std::collections::HashSet::new();
+            let keys: Vec<&str> =
+                self.props.iter().map(|(k, _)| k.as_str()).filter(|k| seen.insert(*k)).collect();
+            visitor.visit_map(PropertiesMapAccess { props: self.props,
+                                                     keys: keys.into_iter(),
+                                                     key: None })
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(self,
+                                                _name: &'static str,
+                                                _fields: &'static [&'static str],
+                                                visitor: V)
+                                                -> Result<V::Value, Error> {
+            self.deserialize_map(visitor)
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct enum identifier ignored_any
+        }
+    }
+
+    struct PropertiesMapAccess<'a> {
+        props: &'a Properties,
+        keys: std::vec::IntoIter<&'a str>,
+        key: Option<&'a str>,
+    }
+
+    impl<'de, 'a> MapAccess<'de> for PropertiesMapAccess<'a> {
+        type Error = Error;
+
+        fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self,
+                                                       seed: K)
+                                                       -> Result<Option<K::Value>, Error> {
+            match self.keys.next() {
+                Some(k) => {
+                    self.key = Some(k);
+                    seed.deserialize(k.into_deserializer()).map(Some)
+                }
+                None => Ok(None),
+            }
+        }
+
+        fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+            let key = self.key.take().ok_or_else(|| de::Error::custom("value requested before key"))?;
+            seed.deserialize(PropertyValueDeserializer { props: self.props, key })
+        }
+    }
+
+    // Deserializes a single key's value; defers to `get_vec` only when
+    // the target field actually asks for a sequence, so a plain scalar
+    // field still reads the first value for a repeated key.
+    struct PropertyValueDeserializer<'a> {
+        props: &'a Properties,
+        key: &'a str,
+    }
+
+    impl<'de, 'a> de::Deserializer<'de> for PropertyValueDeserializer<'a> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            match self.props.get(self.key) {
+                Some(v) => v.as_str().into_deserializer().deserialize_any(visitor),
+                None => Err(de::Error::custom(format!("missing value for key `{}`", self.key))),
+            }
+        }
+
+        fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let values = self.props.get_vec(self.key).unwrap_or_default();
+            visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter().map(|v| v.as_str())))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct map
+            tuple_struct struct tuple enum identifier ignored_any
+        }
+    }
+
+    // ---- Serializer ----
+
+    struct TopSerializer<'a> {
+        ini: &'a mut Ini,
+    }
+
+    impl<'a> ser::Serializer for TopSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+        type SerializeSeq = ser::Impossible<(), Error>;
+        type SerializeTuple = ser::Impossible<(), Error>;
+        type SerializeTupleStruct = ser::Impossible<(), Error>;
+        type SerializeTupleVariant = ser::Impossible<(), Error>;
+        type SerializeMap = TopStructSerializer<'a>;
+        type SerializeStruct = TopStructSerializer<'a>;
+        type SerializeStructVariant = ser::Impossible<(), Error>;
+
+        fn serialize_bool(self, _v: bool) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_i8(self, _v: i8) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_i16(self, _v: i16) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_i32(self, _v: i32) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_i64(self, _v: i64) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_u8(self, _v: u8) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_u16(self, _v: u16) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_u32(self, _v: u32) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_u64(self, _v: u64) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_f32(self, _v: f32) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_f64(self, _v: f64) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_char(self, _v: char) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_str(self, _v: &str) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_none(self) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+            value.serialize(self)
+        }
+        fn serialize_unit(self) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_unit_variant(self,
+                                   _name: &'static str,
+                                   _variant_index: u32,
+                                   _variant: &'static str)
+                                   -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(self,
+                                                             _name: &'static str,
+                                                             value: &T)
+                                                             -> Result<(), Error> {
+            value.serialize(self)
+        }
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(self,
+                                                              _name: &'static str,
+                                                              _variant_index: u32,
+                                                              _variant: &'static str,
+                                                              _value: &T)
+                                                              -> Result<(), Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_tuple_struct(self,
+                                   _name: &'static str,
+                                   _len: usize)
+                                   -> Result<Self::SerializeTupleStruct, Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_tuple_variant(self,
+                                    _name: &'static str,
+                                    _variant_index: u32,
+                                    _variant: &'static str,
+                                    _len: usize)
+                                    -> Result<Self::SerializeTupleVariant, Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+        fn serialize_struct(self,
+                             _name: &'static str,
+                             _len: usize)
+                             -> Result<Self::SerializeStruct, Error> {
+            Ok(TopStructSerializer { ini: self.ini })
+        }
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+            Ok(TopStructSerializer { ini: self.ini })
+        }
+        fn serialize_struct_variant(self,
+                                     _name: &'static str,
+                                     _variant_index: u32,
+                                     _variant: &'static str,
+                                     _len: usize)
+                                     -> Result<Self::SerializeStructVariant, Error> {
+            Err(Error("the top-level value must be a struct or map".to_owned()))
+        }
+    }
+
+    struct TopStructSerializer<'a> {
+        ini: &'a mut Ini,
+    }
+
+    impl<'a> SerializeStruct for TopStructSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self,
+                                                    key: &'static str,
+                                                    value: &T)
+                                                    -> Result<(), Error> {
+            value.serialize(FieldSerializer { ini: self.ini, key: key.to_owned() })
+        }
+
+        fn end(self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl<'a> SerializeMap for TopStructSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_key<T: ?Sized + Serialize>(&mut self, _key: &T) -> Result<(), Error> {
+            Err(Error("non-struct maps are not supported at the top level".to_owned()))
+        }
+
+        fn serialize_value<T: ?Sized + Serialize>(&mut self, _value: &T) -> Result<(), Error> {
+            Err(Error("non-struct maps are not supported at the top level".to_owned()))
+        }
+
+        fn serialize_entry<K: ?Sized + Serialize, V: ?Sized + Serialize>(&mut self,
+                                                                          key: &K,
+                                                                          value: &V)
+                                                                          -> Result<(), Error> {
+            let key = key_to_string(key)?;
+            value.serialize(FieldSerializer { ini: self.ini, key })
+        }
+
+        fn end(self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn key_to_string<K: ?Sized + Serialize>(key: &K) -> Result<String, Error> {
+        key.serialize(ScalarSerializer)
+    }
+
+    // A single top-level field: scalar values go into the general
+    // section under `key`; struct/map values become a `[key]` section.
+    struct FieldSerializer<'a> {
+        ini: &'a mut Ini,
+        key: String,
+    }
+
+    impl<'a> ser::Serializer for FieldSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+        type SerializeSeq = FieldSeqSerializer<'a>;
+        type SerializeTuple = ser::Impossible<(), Error>;
+        type SerializeTupleStruct = ser::Impossible<(), Error>;
+        type SerializeTupleVariant = ser::Impossible<(), Error>;
+        type SerializeMap = SectionSerializer<'a>;
+        type SerializeStruct = SectionSerializer<'a>;
+        type SerializeStructVariant = ser::Impossible<(), Error>;
+
+        fn serialize_bool(self, v: bool) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_i8(self, v: i8) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_i16(self, v: i16) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_i32(self, v: i32) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_i64(self, v: i64) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_u8(self, v: u8) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_u16(self, v: u16) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_u32(self, v: u32) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_u64(self, v: u64) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_f32(self, v: f32) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_f64(self, v: f64) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_char(self, v: char) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_str(self, v: &str) -> Result<(), Error> {
+            self.set(v.to_owned())
+        }
+        fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+            Err(Error("byte fields are not supported".to_owned()))
+        }
+        fn serialize_none(self) -> Result<(), Error> {
+            Ok(())
+        }
+        fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+            value.serialize(self)
+        }
+        fn serialize_unit(self) -> Result<(), Error> {
+            Ok(())
+        }
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+            Ok(())
+        }
+        fn serialize_unit_variant(self,
+                                   _name: &'static str,
+                                   _variant_index: u32,
+                                   variant: &'static str)
+                                   -> Result<(), Error> {
+            self.set(variant.to_owned())
+        }
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(self,
+                                                             _name: &'static str,
+                                                             value: &T)
+                                                             -> Result<(), Error> {
+            value.serialize(self)
+        }
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(self,
+                                                              _name: &'static str,
+                                                              _variant_index: u32,
+                                                              _variant: &'static str,
+                                                              _value: &T)
+                                                              -> Result<(), Error> {
+            Err(Error("newtype variants are not supported".to_owned()))
+        }
+        fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+            Ok(FieldSeqSerializer { ini: self.ini, key: self.key, values: Vec::new() })
+        }
+        fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+            Err(Error("tuples are not supported".to_owned()))
+        }
+        fn serialize_tuple_struct(self,
+                                   _name: &'static str,
+                                   _len: usize)
+                                   -> Result<Self::SerializeTupleStruct, Error> {
+            Err(Error("tuple structs are not supported".to_owned()))
+        }
+        fn serialize_tuple_variant(self,
+                                    _name: &'static str,
+                                    _variant_index: u32,
+                                    _variant: &'static str,
+                                    _len: usize)
+                                    -> Result<Self::SerializeTupleVariant, Error> {
+            Err(Error("tuple variants are not supported".to_owned()))
+        }
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+            Ok(SectionSerializer { ini: self.ini, section: self.key.to_owned() })
+        }
+        fn serialize_struct(self,
+                             _name: &'static str,
+                             _len: usize)
+                             -> Result<Self::SerializeStruct, Error> {
+            Ok(SectionSerializer { ini: self.ini, section: self.key.to_owned() })
+        }
+        fn serialize_struct_variant(self,
+                                     _name: &'static str,
+                                     _variant_index: u32,
+                                     _variant: &'static str,
+                                     _len: usize)
+                                     -> Result<Self::SerializeStructVariant, Error> {
+            Err(Error("struct variants are not supported".to_owned()))
+        }
+    }
+
+    impl<'a> FieldSerializer<'a> {
+        fn set(self, value: String) -> Result<(), Error> {
+            self.ini.with_section(None::<String>).set(self.key, value);
+            Ok(())
+        }
+    }
+
+    struct FieldSeqSerializer<'a> {
+        ini: &'a mut Ini,
+        key: String,
+        values: Vec<String>,
+    }
+
+    impl<'a> ser::SerializeSeq for FieldSeqSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.values.push(value.serialize(ScalarSerializer)?);
+            Ok(())
+        }
+
+        fn end(self) -> Result<(), Error> {
+            for value in self.values {
+                self.ini.with_section(None::<String>).set(self.key.clone(), value);
+            }
+            Ok(())
+        }
+    }
+
+    // A section's fields: scalars become `key = value` entries, and a
+    // `Vec<String>` field becomes repeated `key = value` entries.
+    struct SectionSerializer<'a> {
+        ini: &'a mut Ini,
+        section: String,
+    }
+
+    impl<'a> SerializeStruct for SectionSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_field<T: ?Sized + Serialize>(&mut self,
+                                                    key: &'static str,
+                                                    value: &T)
+                                                    -> Result<(), Error> {
+            self.set_field(key.to_owned(), value)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl<'a> SerializeMap for SectionSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_key<T: ?Sized + Serialize>(&mut self, _key: &T) -> Result<(), Error> {
+            Err(Error("use serialize_entry for section maps".to_owned()))
+        }
+
+        fn serialize_value<T: ?Sized + Serialize>(&mut self, _value: &T) -> Result<(), Error> {
+            Err(Error("use serialize_entry for section maps".to_owned()))
+        }
+
+        fn serialize_entry<K: ?Sized + Serialize, V: ?Sized + Serialize>(&mut self,
+                                                                          key: &K,
+                                                                          value: &V)
+                                                                          -> Result<(), Error> {
+            let key = key_to_string(key)?;
+            self.set_field(key, value)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    impl<'a> SectionSerializer<'a> {
+        fn set_field<T: ?Sized + Serialize>(&mut self, key: String, value: &T) -> Result<(), Error> {
+            match value.serialize(SectionValueSerializer { ini: self.ini,
+                                                            section: &self.section,
+                                                            key }) {
+                Ok(()) => Ok(()),
+                Err(e) => Err(e),
+            }
+        }
+    }
+
+    // A single key within a section; like `FieldSerializer`, a sequence
+    // becomes repeated keys rather than a single value.
+    struct SectionValueSerializer<'a> {
+        ini: &'a mut Ini,
+        section: &'a str,
+        key: String,
+    }
+
+    impl<'a> ser::Serializer for SectionValueSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+        type SerializeSeq = SectionSeqSerializer<'a>;
+        type SerializeTuple = ser::Impossible<(), Error>;
+        type SerializeTupleStruct = ser::Impossible<(), Error>;
+        type SerializeTupleVariant = ser::Impossible<(), Error>;
+        type SerializeMap = ser::Impossible<(), Error>;
+        type SerializeStruct = ser::Impossible<(), Error>;
+        type SerializeStructVariant = ser::Impossible<(), Error>;
+
+        fn serialize_bool(self, v: bool) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_i8(self, v: i8) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_i16(self, v: i16) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_i32(self, v: i32) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_i64(self, v: i64) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_u8(self, v: u8) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_u16(self, v: u16) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_u32(self, v: u32) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_u64(self, v: u64) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_f32(self, v: f32) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_f64(self, v: f64) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_char(self, v: char) -> Result<(), Error> {
+            self.set(v.to_string())
+        }
+        fn serialize_str(self, v: &str) -> Result<(), Error> {
+            self.set(v.to_owned())
+        }
+        fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+            Err(Error("byte fields are not supported".to_owned()))
+        }
+        fn serialize_none(self) -> Result<(), Error> {
+            Ok(())
+        }
+        fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+            value.serialize(self)
+        }
+        fn serialize_unit(self) -> Result<(), Error> {
+            Ok(())
+        }
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+            Ok(())
+        }
+        fn serialize_unit_variant(self,
+                                   _name: &'static str,
+                                   _variant_index: u32,
+                                   variant: &'static str)
+                                   -> Result<(), Error> {
+            self.set(variant.to_owned())
+        }
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(self,
+                                                             _name: &'static str,
+                                                             value: &T)
+                                                             -> Result<(), Error> {
+            value.serialize(self)
+        }
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(self,
+                                                              _name: &'static str,
+                                                              _variant_index: u32,
+                                                              _variant: &'static str,
+                                                              _value: &T)
+                                                              -> Result<(), Error> {
+            Err(Error("newtype variants are not supported".to_owned()))
+        }
+        fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+            Ok(SectionSeqSerializer { ini: self.ini,
+                                       section: self.section.to_owned(),
+                                       key: self.key,
+                                       values: Vec::new() })
+        }
+        fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+            Err(Error("tuples are not supported".to_owned()))
+        }
+        fn serialize_tuple_struct(self,
+                                   _name: &'static str,
+                                   _len: usize)
+                                   -> Result<Self::SerializeTupleStruct, Error> {
+            Err(Error("tuple structs are not supported".to_owned()))
+        }
+        fn serialize_tuple_variant(self,
+                                    _name: &'static str,
+                                    _variant_index: u32,
+                                    _variant: &'static str,
+                                    _len: usize)
+                                    -> Result<Self::SerializeTupleVariant, Error> {
+            Err(Error("tuple variants are not supported".to_owned()))
+        }
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+            Err(Error("nested maps inside a section are not supported".to_owned()))
+        }
+        fn serialize_struct(self,
+                             _name: &'static str,
+                             _len: usize)
+                             -> Result<Self::SerializeStruct, Error> {
+            Err(Error("nested structs inside a section are not supported".to_owned()))
+        }
+        fn serialize_struct_variant(self,
+                                     _name: &'static str,
+                                     _variant_index: u32,
+                                     _variant: &'static str,
+                                     _len: usize)
+                                     -> Result<Self::SerializeStructVariant, Error> {
+            Err(Error("struct variants are not supported".to_owned()))
+        }
+    }
+
+    impl<'a> SectionValueSerializer<'a> {
+        fn set(self, value: String) -> Result<(), Error> {
+            self.ini.with_section(Some(self.section.to_owned())).set(self.key, value);
+            Ok(())
+        }
+    }
+
+    struct SectionSeqSerializer<'a> {
+        ini: &'a mut Ini,
+        section: String,
+        key: String,
+        values: Vec<String>,
+    }
+
+    impl<'a> ser::SerializeSeq for SectionSeqSerializer<'a> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+            self.values.push(value.serialize(ScalarSerializer)?);
+            Ok(())
+        }
+
+        fn end(self) -> Result<(), Error> {
+            for value in self.values {
+                self.ini.with_section(Some(self.section.clone())).set(self.key.clone(), value);
+            }
+            Ok(())
+        }
+    }
+
+    // Converts a scalar `Serialize` value (map keys, sequence elements)
+    // into its textual form without needing a live `Ini` to write into.
+    struct ScalarSerializer;
+
+    impl ser::Serializer for ScalarSerializer {
+        type Ok = String;
+        type Error = Error;
+        type SerializeSeq = ser::Impossible<String, Error>;
+        type SerializeTuple = ser::Impossible<String, Error>;
+        type SerializeTupleStruct = ser::Impossible<String, Error>;
+        type SerializeTupleVariant = ser::Impossible<String, Error>;
+        type SerializeMap = ser::Impossible<String, Error>;
+        type SerializeStruct = ser::Impossible<String, Error>;
+        type SerializeStructVariant = ser::Impossible<String, Error>;
+
+        fn serialize_bool(self, v: bool) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_i8(self, v: i8) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_i16(self, v: i16) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_i32(self, v: i32) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_i64(self, v: i64) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_u8(self, v: u8) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_u16(self, v: u16) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_u32(self, v: u32) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_u64(self, v: u64) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_f32(self, v: f32) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_f64(self, v: f64) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_char(self, v: char) -> Result<String, Error> {
+            Ok(v.to_string())
+        }
+        fn serialize_str(self, v: &str) -> Result<String, Error> {
+            Ok(v.to_owned())
+        }
+        fn serialize_bytes(self, _v: &[u8]) -> Result<String, Error> {
+            Err(Error("byte values are not supported".to_owned()))
+        }
+        fn serialize_none(self) -> Result<String, Error> {
+            Err(Error("optional values are not supported here".to_owned()))
+        }
+        fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<String, Error> {
+            value.serialize(self)
+        }
+        fn serialize_unit(self) -> Result<String, Error> {
+            Err(Error("unit values are not supported here".to_owned()))
+        }
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<String, Error> {
+            Err(Error("unit structs are not supported here".to_owned()))
+        }
+        fn serialize_unit_variant(self,
+                                   _name: &'static str,
+                                   _variant_index: u32,
+                                   variant: &'static str)
+                                   -> Result<String, Error> {
+            Ok(variant.to_owned())
+        }
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(self,
+                                                             _name: &'static str,
+                                                             value: &T)
+                                                             -> Result<String, Error> {
+            value.serialize(self)
+        }
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(self,
+                                                              _name: &'static str,
+                                                              _variant_index: u32,
+                                                              _variant: &'static str,
+                                                              _value: &T)
+                                                              -> Result<String, Error> {
+            Err(Error("newtype variants are not supported here".to_owned()))
+        }
+        fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+            Err(Error("sequences are not supported here".to_owned()))
+        }
+        fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+            Err(Error("tuples are not supported here".to_owned()))
+        }
+        fn serialize_tuple_struct(self,
+                                   _name: &'static str,
+                                   _len: usize)
+                                   -> Result<Self::SerializeTupleStruct, Error> {
+            Err(Error("tuple structs are not supported here".to_owned()))
+        }
+        fn serialize_tuple_variant(self,
+                                    _name: &'static str,
+                                    _variant_index: u32,
+                                    _variant: &'static str,
+                                    _len: usize)
+                                    -> Result<Self::SerializeTupleVariant, Error> {
+            Err(Error("tuple variants are not supported here".to_owned()))
+        }
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+            Err(Error("maps are not supported here".to_owned()))
+        }
+        fn serialize_struct(self,
+                             _name: &'static str,
+                             _len: usize)
+                             -> Result<Self::SerializeStruct, Error> {
+            Err(Error("structs are not supported here".to_owned()))
+        }
+        fn serialize_struct_variant(self,
+                                     _name: &'static str,
+                                     _variant_index: u32,
+                                     _variant: &'static str,
+                                     _len: usize)
+                                     -> Result<Self::SerializeStructVariant, Error> {
+            Err(Error("struct variants are not supported here".to_owned()))
+        }
+    }
+}
+
+/// A zero-copy binary snapshot format for [`Ini`].
+///
+/// [`Ini::to_archived`] serializes an `Ini` into a single contiguous
+/// buffer: a fixed-size header and section/entry tables holding
+/// `(offset, length)` pairs, followed by a blob of the raw UTF-8 bytes
+/// those pairs point into. [`ArchivedConfig::access`] validates every
+/// offset and length against the buffer (and that every string they
+/// reference is valid UTF-8) in a single pass, then hands back `&str`
+/// views that borrow directly from the buffer -- loading becomes a
+/// `mmap`/`memcpy` plus this validation pass, instead of line-by-line
+/// parsing.
+///
+/// This trades generality for speed: unlike [`Ini`], an `ArchivedConfig`
+/// only supports a single value per key (the first one written wins, same
+/// as [`Properties::get`]) and has no mutation API -- round-trip through
+/// `Ini` to change a value, then call `to_archived` again.
+#[cfg(feature = "archive")]
+pub mod archive {
+    use super::*;
+    use std::convert::TryInto;
+
+    const MAGIC: [u8; 4] = *b"INIA";
+    const VERSION: u32 = 1;
+
+    const HEADER_LEN: usize = 4 + 4 + 4;
+    const SECTION_RECORD_LEN: usize = 4 + 4 + 4 + 4 + 4;
+    const ENTRY_RECORD_LEN: usize = 4 + 4 + 4 + 4;
+
+    /// An error returned while validating a buffer in
+    /// [`ArchivedConfig::access`].
+    #[derive(Debug)]
+    pub struct ArchiveError(String);
+
+    impl fmt::Display for ArchiveError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl error::Error for ArchiveError {}
+
+    fn push_u32(buf: &mut Vec<u8>, v: u32) {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn read_u32(buf: &[u8], at: usize) -> Result<u32, ArchiveError> {
+        let end = at.checked_add(4).ok_or_else(|| ArchiveError("offset overflow".to_owned()))?;
+        let slice = buf.get(at..end)
+                       .ok_or_else(|| ArchiveError(format!("field at {} is out of bounds", at)))?;
+        Ok(u32::from_le_bytes(slice.try_into().unwrap()))
+    }
+
+    fn read_str<'a>(buf: &'a [u8], off: u32, len: u32) -> Result<&'a str, ArchiveError> {
+        let start = off as usize;
+        let end = start.checked_add(len as usize)
+                       .ok_or_else(|| ArchiveError("string length overflow".to_owned()))?;
+        let bytes = buf.get(start..end)
+                       .ok_or_else(|| ArchiveError(format!("string at {}..{} is out of bounds", start, end)))?;
+        str::from_utf8(bytes).map_err(|e| ArchiveError(format!("invalid utf-8 in archive: {}", e)))
+    }
+
+    impl Ini {
+        /// Serialize into the zero-copy binary snapshot format. See the
+        /// [`archive`](self) module docs for the layout and its
+        /// limitations.
+        pub fn to_archived(&self) -> Vec<u8> {
+            let sections: Vec<(&Option<String>, &Properties)> = self.sections.iter().collect();
+
+            let mut tables_len = SECTION_RECORD_LEN * sections.len();
+            for (_, props) in &sections {
+                tables_len += ENTRY_RECORD_LEN * props.len();
+            }
+            let blob_start = HEADER_LEN + tables_len;
+
+            let mut header_and_tables = Vec::with_capacity(HEADER_LEN + tables_len);
+            header_and_tables.extend_from_slice(&MAGIC);
+            push_u32(&mut header_and_tables, VERSION);
+            push_u32(&mut header_and_tables, sections.len() as u32);
+
+            let mut blob: Vec<u8> = Vec::new();
+            let push_string = |s: &str, blob: &mut Vec<u8>| -> (u32, u32) {
+                let off = (blob_start + blob.len()) as u32;
+                blob.extend_from_slice(s.as_bytes());
+                (off, s.len() as u32)
+            };
+
+            // Reserve space for the section table up front; entry tables
+            // for each section are appended right after it, in order, so
+            // each section's `entry_off` is known before its record is
+            // written.
+            let section_table_start = header_and_tables.len();
+            header_and_tables.resize(section_table_start + SECTION_RECORD_LEN * sections.len(), 0);
+            let mut entry_tables: Vec<u8> = Vec::new();
+
+            for (i, (name, props)) in sections.iter().enumerate() {
+                let (has_name, name_off, name_len) = match name {
+                    Some(n) => {
+                        let (off, len) = push_string(n, &mut blob);
+                        (1u32, off, len)
+                    }
+                    None => (0u32, 0u32, 0u32),
+                };
+
+                let entry_off = (section_table_start
+                                  + SECTION_RECORD_LEN * sections.len()
+                                  + entry_tables.len()) as u32;
+
+                for (k, v) in props.iter() {
+                    let (key_off, key_len) = push_string(k, &mut blob);
+                    let (val_off, val_len) = push_string(v, &mut blob);
+                    push_u32(&mut entry_tables, key_off);
+                    push_u32(&mut entry_tables, key_len);
+                    push_u32(&mut entry_tables, val_off);
+                    push_u32(&mut entry_tables, val_len);
+                }
+
+                let record_at = section_table_start + SECTION_RECORD_LEN * i;
+                let record = &mut header_and_tables[record_at..record_at + SECTION_RECORD_LEN];
+                record[0..4].copy_from_slice(&has_name.to_le_bytes());
+                record[4..8].copy_from_slice(&name_off.to_le_bytes());
+                record[8..12].copy_from_slice(&name_len.to_le_bytes());
+                record[12..16].copy_from_slice(&entry_off.to_le_bytes());
+                record[16..20].copy_from_slice(&(props.len() as u32).to_le_bytes());
+            }
+
+            header_and_tables.extend_from_slice(&entry_tables);
+            header_and_tables.extend_from_slice(&blob);
+            header_and_tables
+        }
+    }
+
+    struct ArchivedSection<'a> {
+        name: Option<&'a str>,
+        entry_off: u32,
+        entry_count: u32,
+    }
+
+    /// A validated, borrowed view over a buffer produced by
+    /// [`Ini::to_archived`]. Every `&str` handed back borrows directly
+    /// from the buffer passed to [`access`](ArchivedConfig::access); no
+    /// copying happens after validation.
+    pub struct ArchivedConfig<'a> {
+        buf: &'a [u8],
+        sections: Vec<ArchivedSection<'a>>,
+    }
+
+    impl<'a> ArchivedConfig<'a> {
+        /// Validate `buf` as an archive produced by [`Ini::to_archived`]
+        /// and build a borrowed view over it. Every offset and length in
+        /// the header and tables is checked against `buf`, and every
+        /// string they reference is checked for valid UTF-8, before this
+        /// returns -- a corrupt buffer is rejected here rather than
+        /// producing a dangling or invalid `&str` later.
+        pub fn access(buf: &'a [u8]) -> Result<ArchivedConfig<'a>, ArchiveError> {
+            if buf.len() < HEADER_LEN || buf[0..4] != MAGIC {
+                return Err(ArchiveError("not an ini archive (bad magic)".to_owned()));
+            }
+
+            let version = read_u32(buf, 4)?;
+            if version != VERSION {
+                return Err(ArchiveError(format!("unsupported archive version {}", version)));
+            }
+
+            let section_count = read_u32(buf, 8)? as usize;
+            // Bound the claimed count against what the buffer could
+            // actually hold before trusting it as a `Vec::with_capacity`
+            // argument -- an inflated count field must not reach an
+            // allocation before it has been checked against the bytes
+            // actually available, or it aborts the process.
+            let max_section_count = buf.len().saturating_sub(HEADER_LEN) / SECTION_RECORD_LEN;
+            if section_count > max_section_count {
+                return Err(ArchiveError(format!("section count {} exceeds buffer capacity",
+                                                 section_count)));
+            }
+            let mut sections = Vec::with_capacity(section_count);
+
+            for i in 0..section_count {
+                let record_at = HEADER_LEN + SECTION_RECORD_LEN * i;
+                let has_name = read_u32(buf, record_at)?;
+                let name_off = read_u32(buf, record_at + 4)?;
+                let name_len = read_u32(buf, record_at + 8)?;
+                let entry_off = read_u32(buf, record_at + 12)?;
+                let entry_count = read_u32(buf, record_at + 16)?;
+
+                let name = if has_name != 0 {
+                    Some(read_str(buf, name_off, name_len)?)
+                } else {
+                    None
+                };
+
+                // Same bounds check as `section_count` above, against the
+                // bytes remaining after this section's entry table starts.
+                let max_entry_count = buf.len().saturating_sub(entry_off as usize) / ENTRY_RECORD_LEN;
+                if entry_count as usize > max_entry_count {
+                    return Err(ArchiveError(format!("entry count {} exceeds buffer capacity",
+                                                     entry_count)));
+                }
+
+                // Validate every entry's offsets up front too, so a
+                // corrupt table is caught here rather than when a
+                // specific key is later looked up.
+                for j in 0..entry_count as usize {
+                    let entry_at = entry_off as usize + ENTRY_RECORD_LEN * j;
+                    let key_off = read_u32(buf, entry_at)?;
+                    let key_len = read_u32(buf, entry_at + 4)?;
+                    let val_off = read_u32(buf, entry_at + 8)?;
+                    let val_len = read_u32(buf, entry_at + 12)?;
+                    read_str(buf, key_off, key_len)?;
+                    read_str(buf, val_off, val_len)?;
+                }
+
+                sections.push(ArchivedSection { name, entry_off, entry_count });
+            }
+
+            Ok(ArchivedConfig { buf, sections })
+        }
+
+        /// Look up a key in `section` (`None` for the general section).
+        /// If the key was written more than once, the first value wins,
+        /// matching [`Properties::get`].
+        pub fn get(&self, section: Option<&str>, key: &str) -> Option<&'a str> {
+            let sec = self.sections.iter().find(|s| s.name == section)?;
+            for j in 0..sec.entry_count as usize {
+                let entry_at = sec.entry_off as usize + ENTRY_RECORD_LEN * j;
+                // Bounds and UTF-8 validity were already checked in
+                // `access`, so these reads cannot fail here.
+                let key_off = read_u32(self.buf, entry_at).unwrap();
+                let key_len = read_u32(self.buf, entry_at + 4).unwrap();
+                let k = read_str(self.buf, key_off, key_len).unwrap();
+                if k == key {
+                    let val_off = read_u32(self.buf, entry_at + 8).unwrap();
+                    let val_len = read_u32(self.buf, entry_at + 12).unwrap();
+                    return Some(read_str(self.buf, val_off, val_len).unwrap());
+                }
+            }
+            None
+        }
+
+        /// Iterate over the archive's section names (`None` for the
+        /// general section), in the order they were written.
+        pub fn sections(&self) -> impl Iterator<Item = Option<&'a str>> + '_ {
+            self.sections.iter().map(|s| s.name)
+        }
+    }
+}
+
+impl<'a> Parser<'a> {
+    // Create a parser
+    pub fn new(rdr: Chars<'a>, opt: ParseOption) -> Parser<'a> {
+        let mut p = Parser { ch: None,
+                             line: 0,
+                             col: 0,
+                             rdr,
+                             opt };
+        p.bump();
+        p
+    }
+
+    fn eof(&self) -> bool {
+        self.ch.is_none()
+    }
+
+    fn bump(&mut self) {
+        self.ch = self.rdr.next();
+        match self.ch {
+            Some('\n') => {
+                self.line += 1;
+                self.col = 0;
+            }
+            Some(..) => {
+                self.col += 1;
+            }
+            None => {}
+        }
+    }
+
+    fn make_error<M: Into<String>>(&self, msg: M) -> ParseError {
+        ParseError { line: self.line,
+                     col: self.col,
+                     msg: msg.into() }
+    }
+
+    fn error<U, M: Into<String>>(&self, msg: M) -> Result<U, ParseError> {
+        Err(self.make_error(msg))
+    }
+
+    /// Consume all the white space until the end of the line or a tab
     fn parse_whitespace(&mut self) {
         while let Some(c) = self.ch {
             if !c.is_whitespace() && c != '\n' && c != '\t' && c != '\r' {
@@ -928,20 +3423,19 @@ impl<'a> Parser<'a> {
 
         self.parse_whitespace();
         while let Some(cur_ch) = self.ch {
-            match cur_ch {
-                ';' | '#' => {
-                    if cfg!(not(feature = "inline_comment")) {
-                        // Inline comments is not supported, so comments must starts from a new line
-                        //
-                        // https://en.wikipedia.org/wiki/INI_file#Comments
-                        if self.col > 1 {
-                            return self.error("doesn't support inline comment");
-                        }
+            if self.opt.comment_markers.contains(&cur_ch) {
+                if !self.opt.inline_comment {
+                    // Inline comments is not supported, so comments must starts from a new line
+                    //
+                    // https://en.wikipedia.org/wiki/INI_file#Comments
+                    if self.col > 1 {
+                        return self.error("doesn't support inline comment");
                     }
-
-                    self.parse_comment();
                 }
-                '[' => match self.parse_section() {
+
+                self.parse_comment();
+            } else if cur_ch == '[' {
+                match self.parse_section() {
                     Ok(sec) => {
                         let msec = &sec[..].trim();
                         cursec = Some(msec.to_string());
@@ -949,34 +3443,123 @@ impl<'a> Parser<'a> {
                         self.bump();
                     }
                     Err(e) => return Err(e),
-                },
-                '=' | ':' => {
-                    if (&curkey[..]).is_empty() {
-                        return self.error("missing key");
+                }
+            } else if self.opt.delimiters.contains(&cur_ch) {
+                if (&curkey[..]).is_empty() {
+                    return self.error("missing key");
+                }
+                match self.parse_val() {
+                    Ok(val) => {
+                        let sec = result.sections.entry(cursec.clone()).or_insert_with(Default::default);
+                        sec.insert(curkey, val);
+                        curkey = "".into();
+                    }
+                    Err(e) => return Err(e),
+                }
+            } else {
+                match self.parse_key() {
+                    Ok(key) => {
+                        let mkey: String = key[..].trim().to_owned();
+                        curkey = mkey;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            self.parse_whitespace();
+        }
+
+        Ok(result)
+    }
+
+    /// Parse the whole INI input, recovering from errors instead of
+    /// aborting on the first one.
+    ///
+    /// Each error is recorded with its line/col, the rest of the current
+    /// logical line is discarded via [`resync`](Parser::resync), and
+    /// parsing resumes on the next line, accumulating whatever was
+    /// successfully parsed into the returned `Ini`. A fully valid input
+    /// yields an empty error vector and an `Ini` identical to what
+    /// [`parse`](Parser::parse) would have produced.
+    pub fn parse_recover(&mut self) -> (Ini, Vec<ParseError>) {
+        let mut result = Ini::new();
+        let mut errors = Vec::new();
+        let mut curkey: String = "".into();
+        let mut cursec: Option<String> = None;
+
+        self.parse_whitespace();
+        while let Some(cur_ch) = self.ch {
+            if self.opt.comment_markers.contains(&cur_ch) {
+                if !self.opt.inline_comment && self.col > 1 {
+                    errors.push(self.make_error("doesn't support inline comment"));
+                    self.resync();
+                    curkey = "".into();
+                    self.parse_whitespace();
+                    continue;
+                }
+
+                self.parse_comment();
+            } else if cur_ch == '[' {
+                match self.parse_section() {
+                    Ok(sec) => {
+                        let msec = &sec[..].trim();
+                        cursec = Some(msec.to_string());
+                        result.sections.entry(cursec.clone()).or_insert_with(Default::default);
+                        self.bump();
                     }
+                    Err(e) => {
+                        errors.push(e);
+                        self.resync();
+                        curkey = "".into();
+                    }
+                }
+            } else if self.opt.delimiters.contains(&cur_ch) {
+                if (&curkey[..]).is_empty() {
+                    errors.push(self.make_error("missing key"));
+                    self.resync();
+                } else {
                     match self.parse_val() {
                         Ok(val) => {
-                            let mval = val[..].trim().to_owned();
                             let sec = result.sections.entry(cursec.clone()).or_insert_with(Default::default);
-                            sec.insert(curkey, mval);
-                            curkey = "".into();
+                            sec.insert(curkey, val);
+                        }
+                        Err(e) => {
+                            errors.push(e);
+                            self.resync();
                         }
-                        Err(e) => return Err(e),
                     }
+                    curkey = "".into();
                 }
-                _ => match self.parse_key() {
+            } else {
+                match self.parse_key() {
                     Ok(key) => {
                         let mkey: String = key[..].trim().to_owned();
                         curkey = mkey;
                     }
-                    Err(e) => return Err(e),
-                },
+                    Err(e) => {
+                        errors.push(e);
+                        self.resync();
+                        curkey = "".into();
+                    }
+                }
             }
 
             self.parse_whitespace();
         }
 
-        Ok(result)
+        (result, errors)
+    }
+
+    /// Discard the rest of the current logical line, used by
+    /// [`parse_recover`](Parser::parse_recover) to resynchronize after an
+    /// error and resume parsing on the next line.
+    fn resync(&mut self) {
+        while let Some(c) = self.ch {
+            self.bump();
+            if c == '\n' {
+                break;
+            }
+        }
     }
 
     fn parse_comment(&mut self) {
@@ -988,6 +3571,19 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// If `self.ch` is `'\'` and it is immediately followed by a line break
+    /// (`\n` or `\r\n`), returns how many characters make up that line
+    /// break (1 or 2). Used to recognize continuation lines without
+    /// consuming input.
+    fn continuation_len(&self) -> Option<usize> {
+        let mut rest = self.rdr.clone();
+        match rest.next() {
+            Some('\n') => Some(1),
+            Some('\r') if rest.next() == Some('\n') => Some(2),
+            _ => None,
+        }
+    }
+
     fn parse_str_until(&mut self, endpoint: &[Option<char>]) -> Result<String, ParseError> {
         let mut result: String = String::new();
 
@@ -996,6 +3592,15 @@ impl<'a> Parser<'a> {
                 None => {
                     return self.error(format!("expecting \"{:?}\" but found EOF.", endpoint));
                 }
+                Some('\\') if self.opt.enabled_continuation && self.continuation_len().is_some() => {
+                    let line_break_len = self.continuation_len().unwrap();
+                    self.bump(); // Skip the backslash
+                    for _ in 0..line_break_len {
+                        self.bump(); // Skip the line break
+                    }
+                    self.parse_whitespace_except_line_break();
+                    continue;
+                }
                 Some('\\') if self.opt.enabled_escape => {
                     self.bump();
                     if self.eof() {
@@ -1051,7 +3656,8 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_key(&mut self) -> Result<String, ParseError> {
-        self.parse_str_until(&[Some('='), Some(':')])
+        let endpoint: Vec<Option<char>> = self.opt.delimiters.iter().map(|&c| Some(c)).collect();
+        self.parse_str_until(&endpoint)
     }
 
     fn parse_val(&mut self) -> Result<String, ParseError> {
@@ -1061,12 +3667,18 @@ impl<'a> Parser<'a> {
 
         match self.ch {
             None => Ok(String::new()),
+            // Quoted values are returned as-is: the quotes mark the exact
+            // boundary of the value, so (unlike the unquoted case below)
+            // leading/trailing whitespace inside them must not be
+            // trimmed away. Only trailing text found after the closing
+            // quote (ordinarily just whitespace before EOL or a comment)
+            // is trimmed.
             Some('"') if self.opt.enabled_quote => {
                 self.bump();
                 self.parse_str_until(&[Some('"')]).and_then(|s| {
                                                       self.bump(); // Eats the last "
                                                                    // Parse until EOL
-                                                      self.parse_str_until_eol().map(|x| s + &x)
+                                                      self.parse_str_until_eol().map(|x| s + x.trim_end())
                                                   })
             }
             Some('\'') if self.opt.enabled_quote => {
@@ -1074,21 +3686,19 @@ impl<'a> Parser<'a> {
                 self.parse_str_until(&[Some('\'')]).and_then(|s| {
                                                        self.bump(); // Eats the last '
                                                                     // Parse until EOL
-                                                       self.parse_str_until_eol().map(|x| s + &x)
+                                                       self.parse_str_until_eol().map(|x| s + x.trim_end())
                                                    })
             }
-            _ => self.parse_str_until_eol(),
+            _ => self.parse_str_until_eol().map(|s| s.trim().to_owned()),
         }
     }
 
-    #[cfg(not(feature = "inline_comment"))]
-    fn parse_str_until_eol(&mut self) -> Result<String, ParseError> {
-        self.parse_str_until(&[Some('\n'), Some('\r'), None])
-    }
-
-    #[cfg(feature = "inline_comment")]
     fn parse_str_until_eol(&mut self) -> Result<String, ParseError> {
-        self.parse_str_until(&[Some('\n'), Some('\r'), Some(';'), Some('#'), None])
+        let mut endpoint: Vec<Option<char>> = vec![Some('\n'), Some('\r'), None];
+        if self.opt.inline_comment {
+            endpoint.extend(self.opt.comment_markers.iter().map(|&c| Some(c)));
+        }
+        self.parse_str_until(&endpoint)
     }
 }
 
@@ -1435,6 +4045,538 @@ Exec = \"/path/to/exe with space\" arg
         let sec = opt.section(Some("Desktop Entry")).unwrap();
         assert_eq!(sec["Exec"], "\"/path/to/exe with space\" arg");
     }
+
+    #[test]
+    fn custom_comment_markers_and_delimiters_are_honored() {
+        let input = "
+[section name]
+name > hello @ trailing note
+gender > mail
+";
+
+        let ini = Ini::load_from_str_opt(input,
+                                         ParseOption { comment_markers: vec!['@'],
+                                                       delimiters: vec!['>'],
+                                                       inline_comment: true,
+                                                       ..ParseOption::default() }).unwrap();
+        assert_eq!(ini.get_from(Some("section name"), "name").unwrap(), "hello");
+        assert_eq!(ini.get_from(Some("section name"), "gender").unwrap(), "mail");
+    }
+
+    #[test]
+    fn default_delimiters_are_not_delimiters_under_custom_config() {
+        // With '=' and ':' no longer configured as delimiters, a line using
+        // them should not be split into a key/value pair by '='.
+        let input = "
+[section name]
+name > a=b
+";
+
+        let ini = Ini::load_from_str_opt(input,
+                                         ParseOption { delimiters: vec!['>'],
+                                                       ..ParseOption::default() }).unwrap();
+        assert_eq!(ini.get_from(Some("section name"), "name").unwrap(), "a=b");
+    }
+
+    #[test]
+    fn enabled_continuation_joins_backslash_continued_lines() {
+        let input = "
+[section name]
+name = part1 \\
+part2
+";
+
+        let ini = Ini::load_from_str_opt(input,
+                                         ParseOption { enabled_continuation: true,
+                                                       ..ParseOption::default() }).unwrap();
+        assert_eq!(ini.get_from(Some("section name"), "name").unwrap(), "part1 part2");
+    }
+
+    #[test]
+    fn continuation_disabled_by_default_still_parses_escaped_line_break() {
+        let input = "
+[section name]
+name = part1 \\
+part2
+";
+
+        // Without `enabled_continuation`, a trailing backslash is just an
+        // escape of the following newline character (enabled_escape is on
+        // by default), so this should still parse, not error.
+        let ini = Ini::load_from_str(input).unwrap();
+        assert!(ini.get_from(Some("section name"), "name").is_some());
+    }
+
+    #[test]
+    fn parse_recover_returns_no_errors_for_a_valid_file() {
+        let input = "
+[section name]
+name = hello
+gender = mail
+";
+        let (ini, errors) = Ini::load_from_str_recover(input);
+        assert!(errors.is_empty());
+        assert_eq!(ini.get_from(Some("section name"), "name").unwrap(), "hello");
+        assert_eq!(ini.get_from(Some("section name"), "gender").unwrap(), "mail");
+    }
+
+    #[test]
+    fn parse_recover_accumulates_errors_and_still_returns_the_valid_parts() {
+        let input = "
+[section name]
+name = hello
+= stray value with no key
+gender = mail
+";
+        let (ini, errors) = Ini::load_from_str_recover(input);
+        assert!(!errors.is_empty());
+        assert_eq!(ini.get_from(Some("section name"), "name").unwrap(), "hello");
+        assert_eq!(ini.get_from(Some("section name"), "gender").unwrap(), "mail");
+    }
+
+    #[test]
+    fn include_merges_referenced_file() {
+        let dir = std::env::temp_dir().join("rust-ini-test-include-merge");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let included_path = dir.join("shared.ini");
+        std::fs::write(&included_path, "[sec]\nfrom_include = yes\n").unwrap();
+
+        let main_path = dir.join("main.ini");
+        std::fs::write(&main_path, "[include]\npath = shared.ini\n[sec]\nown = yes\n").unwrap();
+
+        let ini = Ini::load_from_file_opt(&main_path,
+                                          ParseOption { enable_includes: true,
+                                                        ..ParseOption::default() }).unwrap();
+
+        assert!(ini.section(Some("include")).is_none());
+        let sec = ini.section(Some("sec")).unwrap();
+        assert_eq!(sec["from_include"], "yes");
+        assert_eq!(sec["own"], "yes");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_value_overrides_same_key_set_before_the_directive() {
+        let dir = std::env::temp_dir().join("rust-ini-test-include-override");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let included_path = dir.join("shared.ini");
+        std::fs::write(&included_path, "[sec]\nkey = from_include\n").unwrap();
+
+        let main_path = dir.join("main.ini");
+        std::fs::write(&main_path,
+                       "[sec]\nkey = from_main\n[include]\npath = shared.ini\n").unwrap();
+
+        let ini = Ini::load_from_file_opt(&main_path,
+                                          ParseOption { enable_includes: true,
+                                                        ..ParseOption::default() }).unwrap();
+
+        let sec = ini.section(Some("sec")).unwrap();
+        assert_eq!(sec["key"], "from_include");
+        assert_eq!(sec.get_vec("key").unwrap().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_from_file_with_includes_reports_missing_path_as_parse_error() {
+        let dir = std::env::temp_dir().join("rust-ini-test-include-missing");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let main_path = dir.join("main.ini");
+        std::fs::write(&main_path, "[include]\npath = does-not-exist.ini\n").unwrap();
+
+        match Ini::load_from_file_with_includes(&main_path, IncludeOption::default()) {
+            Err(Error::Parse(_)) => {}
+            Err(Error::Io(e)) => panic!("expected Error::Parse for a missing include, got Io({})", e),
+            Ok(_) => panic!("expected Error::Parse for a missing include, got Ok"),
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn include_cycle_is_rejected() {
+        let dir = std::env::temp_dir().join("rust-ini-test-include-cycle");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let a_path = dir.join("a.ini");
+        let b_path = dir.join("b.ini");
+        std::fs::write(&a_path, "[include]\npath = b.ini\n").unwrap();
+        std::fs::write(&b_path, "[include]\npath = a.ini\n").unwrap();
+
+        let result = Ini::load_from_file_opt(&a_path,
+                                             ParseOption { enable_includes: true,
+                                                           ..ParseOption::default() });
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_from_file_opt_reports_missing_include_as_parse_error() {
+        let dir = std::env::temp_dir().join("rust-ini-test-include-opt-missing");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let main_path = dir.join("main.ini");
+        std::fs::write(&main_path, "[include]\npath = does-not-exist.ini\n").unwrap();
+
+        match Ini::load_from_file_opt(&main_path,
+                                      ParseOption { enable_includes: true,
+                                                    ..ParseOption::default() }) {
+            Err(Error::Parse(_)) => {}
+            Err(Error::Io(e)) => panic!("expected Error::Parse for a missing include, got Io({})", e),
+            Ok(_) => panic!("expected Error::Parse for a missing include, got Ok"),
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let input = "
+[sec]
+flag_on = on
+flag_off =
+count = 3
+cache = 64M
+ratio = 1.5
+";
+        let ini = Ini::load_from_str(input).unwrap();
+        let sec = ini.section(Some("sec")).unwrap();
+
+        assert_eq!(sec.get_bool("flag_on").unwrap(), true);
+        assert_eq!(sec.get_bool("flag_off").unwrap(), false);
+        assert_eq!(sec.get_int("count").unwrap(), 3);
+        assert_eq!(sec.get_uint("cache").unwrap(), 64 * 1024 * 1024);
+        assert_eq!(sec.get_float("ratio").unwrap(), 1.5);
+
+        assert!(matches!(sec.get_bool("missing"), Err(GetError::NotPresent)));
+        assert!(matches!(sec.get_int("flag_on"), Err(GetError::InvalidValue { .. })));
+
+        assert_eq!(ini.get_bool_from(Some("sec"), "flag_on").unwrap(), true);
+    }
+
+    #[test]
+    fn config_set_cascades_layers() {
+        let dir = std::env::temp_dir().join("rust-ini-test-config-set");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let system_path = dir.join("system.ini");
+        let user_path = dir.join("user.ini");
+        std::fs::write(&system_path, "[sec]\nkey1 = system\nkey2 = system\n").unwrap();
+        std::fs::write(&user_path, "[sec]\nkey1 = user\n").unwrap();
+
+        let mut set = ConfigSet::new();
+        set.load_path(&system_path).unwrap();
+        set.load_path(&user_path).unwrap();
+
+        assert_eq!(set.get(Some("sec"), "key1"), Some("user"));
+        assert_eq!(set.get(Some("sec"), "key2"), Some("system"));
+
+        let origin = set.get_with_origin(Some("sec"), "key1").unwrap();
+        assert_eq!(origin.value, "user");
+        assert_eq!(origin.path, user_path);
+        assert_eq!(origin.line, Some(2));
+
+        let contributors: Vec<_> = set.layers_for(Some("sec"), "key1").collect();
+        assert_eq!(contributors, vec![system_path.as_path(), user_path.as_path()]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn quote_style_when_needed_round_trips_boundary_whitespace() {
+        let mut ini = Ini::new();
+        ini.with_section(Some("Section")).set("Key", "  padded value  ");
+
+        let mut buf = Vec::new();
+        ini.write_to_opt(&mut buf,
+                         WriteOption { quote_style: QuoteStyle::WhenNeeded,
+                                       ..Default::default() })
+           .unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("Key=\"  padded value  \""));
+
+        let reparsed = Ini::load_from_str(&text).unwrap();
+        assert_eq!(reparsed.get_from(Some("Section"), "Key").unwrap(), "  padded value  ");
+    }
+
+    #[test]
+    fn quote_style_when_needed_round_trips_leading_quote_char() {
+        let mut ini = Ini::new();
+        ini.with_section(Some("Section")).set("Key", "\"quoted-looking\" value");
+
+        let mut buf = Vec::new();
+        ini.write_to_opt(&mut buf,
+                         WriteOption { quote_style: QuoteStyle::WhenNeeded,
+                                       ..Default::default() })
+           .unwrap();
+        let text = String::from_utf8(buf).unwrap();
+
+        let reparsed = Ini::load_from_str(&text).unwrap();
+        assert_eq!(reparsed.get_from(Some("Section"), "Key").unwrap(), "\"quoted-looking\" value");
+    }
+
+    #[test]
+    fn quote_style_when_needed_is_the_default() {
+        let mut ini = Ini::new();
+        ini.with_section(Some("Section")).set("Key", "plain");
+
+        let mut buf = Vec::new();
+        ini.write_to(&mut buf).unwrap();
+        // A value that needs no quoting is unaffected by the default.
+        assert_eq!(String::from_utf8(buf).unwrap(), "[Section]\nKey=plain\n");
+    }
+
+    #[test]
+    fn default_write_round_trips_value_containing_equals() {
+        // Regression test: an embedded `=` must round-trip without an
+        // opt-in `EscapePolicy`/`QuoteStyle`, since it looks like another
+        // `key=value` pair to a naive reader otherwise.
+        let mut ini = Ini::new();
+        ini.with_section(Some("Section")).set("Key", "a=b");
+
+        let mut buf = Vec::new();
+        ini.write_to(&mut buf).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert_eq!(text, "[Section]\nKey=\"a=b\"\n");
+
+        let reparsed = Ini::load_from_str(&text).unwrap();
+        assert_eq!(reparsed.get_from(Some("Section"), "Key").unwrap(), "a=b");
+    }
+
+    #[test]
+    fn load_from_file_reports_invalid_utf8_instead_of_panicking() {
+        let dir = std::env::temp_dir().join("rust_ini_invalid_utf8_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("invalid.ini");
+        std::fs::write(&path, [b'[', b'S', b']', b'\n', b'k', b'=', 0xff, b'\n']).unwrap();
+
+        let result = Ini::load_from_file(&path);
+        assert!(result.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lossless_round_trip_is_byte_exact() {
+        let input = "; leading comment\n\n[Section 1]\nkey1 = value1  # trailing note is not special here\nkey2: value2\n\n# another comment\n[Section 2]\nkey3=value3";
+
+        let doc = Ini::load_from_str_lossless(input).unwrap();
+        let mut buf = Vec::new();
+        doc.write_to(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), input);
+    }
+
+    #[test]
+    fn lossless_set_only_touches_changed_value() {
+        let input = "[Section]\nkey1 = value1\nkey2 = value2\n";
+
+        let mut doc = Ini::load_from_str_lossless(input).unwrap();
+        assert!(doc.set(Some("Section"), "key1", "changed"));
+        assert_eq!(doc.get(Some("Section"), "key1"), Some("changed"));
+        assert_eq!(doc.get(Some("Section"), "key2"), Some("value2"));
+
+        let mut buf = Vec::new();
+        doc.write_to(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "[Section]\nkey1 = changed\nkey2 = value2\n");
+
+        assert!(!doc.set(Some("Section"), "missing", "x"));
+    }
+
+    #[test]
+    fn lossless_remove_deletes_only_its_line() {
+        let input = "[Section]\nkey1 = value1\n# keep me\nkey2 = value2\n";
+
+        let mut doc = Ini::load_from_str_lossless(input).unwrap();
+        assert!(doc.remove(Some("Section"), "key1"));
+        assert_eq!(doc.get(Some("Section"), "key1"), None);
+        assert_eq!(doc.get(Some("Section"), "key2"), Some("value2"));
+
+        let mut buf = Vec::new();
+        doc.write_to(&mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "[Section]\n# keep me\nkey2 = value2\n");
+
+        assert!(!doc.remove(Some("Section"), "missing"));
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn archive_round_trips_sections_and_values() {
+        use super::archive::ArchivedConfig;
+
+        let input = "global = g\n[Section]\nkey1 = value1\nkey2 = value2\n[Empty]\n";
+        let ini = Ini::load_from_str(input).unwrap();
+
+        let buf = ini.to_archived();
+        let archived = ArchivedConfig::access(&buf).unwrap();
+
+        assert_eq!(archived.get(None, "global"), Some("g"));
+        assert_eq!(archived.get(Some("Section"), "key1"), Some("value1"));
+        assert_eq!(archived.get(Some("Section"), "key2"), Some("value2"));
+        assert_eq!(archived.get(Some("Section"), "missing"), None);
+        assert_eq!(archived.get(Some("NoSuchSection"), "key1"), None);
+
+        // Iteration order is whatever `Ini`'s own section map yields (a
+        // `HashMap` absent the `preserve_order` feature), so compare as a
+        // set rather than asserting a specific order.
+        let mut names: Vec<Option<&str>> = archived.sections().collect();
+        names.sort();
+        let mut expected = vec![None, Some("Section"), Some("Empty")];
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn archive_access_rejects_corrupt_buffer() {
+        use super::archive::ArchivedConfig;
+
+        assert!(ArchivedConfig::access(b"not an archive").is_err());
+
+        let ini = Ini::load_from_str("[Section]\nkey = value\n").unwrap();
+        let mut buf = ini.to_archived();
+        let len = buf.len();
+        buf.truncate(len - 1);
+        assert!(ArchivedConfig::access(&buf).is_err());
+    }
+
+    #[cfg(feature = "archive")]
+    #[test]
+    fn archive_access_rejects_inflated_section_count_without_allocating() {
+        use super::archive::ArchivedConfig;
+
+        // Valid magic/version header, followed by a section count that
+        // claims far more sections than an ~12-byte buffer could hold.
+        // This must be rejected by a bounds check, not handed to
+        // `Vec::with_capacity` as-is.
+        let mut buf = b"INIA".to_vec();
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(ArchivedConfig::access(&buf).is_err());
+    }
+
+    // Manual `Serialize`/`Deserialize` impls below stand in for
+    // `#[derive(Serialize, Deserialize)]`: this vendor tree takes no
+    // dependency on `serde_derive`, so a test exercising `serde_support`
+    // has to hand-write what the derive macro would generate.
+    #[cfg(feature = "serde")]
+    struct ServerConfig {
+        host: String,
+    }
+
+    #[cfg(feature = "serde")]
+    impl serde::ser::Serialize for ServerConfig {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where S: serde::ser::Serializer
+        {
+            use serde::ser::SerializeStruct;
+            let mut s = serializer.serialize_struct("ServerConfig", 1)?;
+            s.serialize_field("host", &self.host)?;
+            s.end()
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::de::Deserialize<'de> for ServerConfig {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where D: serde::de::Deserializer<'de>
+        {
+            struct ServerConfigVisitor;
+            impl<'de> serde::de::Visitor<'de> for ServerConfigVisitor {
+                type Value = ServerConfig;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("a ServerConfig struct")
+                }
+
+                fn visit_map<A>(self, mut map: A) -> Result<ServerConfig, A::Error>
+                    where A: serde::de::MapAccess<'de>
+                {
+                    let mut host = None;
+                    while let Some(key) = map.next_key::<String>()? {
+                        if key == "host" {
+                            host = Some(map.next_value::<String>()?);
+                        }
+                    }
+                    let host = host.ok_or_else(|| serde::de::Error::custom("missing `host`"))?;
+                    Ok(ServerConfig { host })
+                }
+            }
+            deserializer.deserialize_map(ServerConfigVisitor)
+        }
+    }
+
+    #[cfg(feature = "capi")]
+    #[test]
+    fn capi_round_trips_through_load_get_write_free() {
+        use super::capi::*;
+        use std::ffi::CString;
+        use std::ptr;
+
+        let dir = std::env::temp_dir().join("rust-ini-test-capi");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capi.ini");
+        std::fs::write(&path, "[Section]\nkey=value\n").unwrap();
+
+        unsafe {
+            let c_path = CString::new(path.to_str().unwrap()).unwrap();
+            let ini = ini_load_path(c_path.as_ptr(), ptr::null_mut());
+            assert!(!ini.is_null());
+            assert_eq!(ini_section_count(ini), 1);
+
+            let c_section = CString::new("Section").unwrap();
+            let c_key = CString::new("key").unwrap();
+            let c_value = ini_get(ini, c_section.as_ptr(), c_key.as_ptr());
+            assert!(!c_value.is_null());
+            assert_eq!(std::ffi::CStr::from_ptr(c_value).to_str().unwrap(), "value");
+            ini_string_free(c_value);
+
+            let c_missing_key = CString::new("missing").unwrap();
+            assert!(ini_get(ini, c_section.as_ptr(), c_missing_key.as_ptr()).is_null());
+
+            let out_path = dir.join("capi-out.ini");
+            let c_out_path = CString::new(out_path.to_str().unwrap()).unwrap();
+            assert_eq!(ini_write_path(ini, c_out_path.as_ptr(), ptr::null_mut()), 0);
+            assert!(std::fs::read_to_string(&out_path).unwrap().contains("key"));
+
+            ini_free(ini);
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "capi")]
+    #[test]
+    fn capi_load_path_reports_error_for_missing_file() {
+        use super::capi::*;
+        use std::ffi::CString;
+        use std::ptr;
+
+        unsafe {
+            let c_path = CString::new("/no/such/path/rust-ini-capi-test.ini").unwrap();
+            let mut err: *mut std::os::raw::c_char = ptr::null_mut();
+            let ini = ini_load_path(c_path.as_ptr(), &mut err);
+            assert!(ini.is_null());
+            assert!(!err.is_null());
+            ini_string_free(err);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_support_round_trips_top_level_field() {
+        use super::serde_support::{from_str, to_string};
+
+        let text = to_string(&ServerConfig { host: "example.com".to_owned() }).unwrap();
+        assert!(text.contains("host=example.com") || text.contains("host = example.com"));
+
+        let config: ServerConfig = from_str(&text).unwrap();
+        assert_eq!(config.host, "example.com");
+    }
 }
 
 #[cfg(test)]